@@ -0,0 +1,141 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Compare allocations of `Waiter` and `NativeWaiter`.
+//!
+//! Both use the `DefaultTimer`, i.e. tokio with the default features.
+//!
+//! Run with `cargo bench --bench allocations`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use waiter::native::NativeWaiter;
+use waiter::timer::DefaultTimer;
+use waiter::{TimedOut, Waiter};
+
+/// Number of `poll` calls in one wait.
+///
+/// Every attempt sleeps until the next tick of the tokio timer, i.e. about a
+/// millisecond.
+const POLLS: u32 = 1_000;
+
+/// Poll timeout, so that the sleeps of both the delays and the poll
+/// timeouts are measured.
+const POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Global allocator counting allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// Waiter finishing after `POLLS` attempts.
+struct Countdown(u32);
+
+impl Countdown {
+    fn step(&mut self) -> Result<Option<u32>, TimedOut> {
+        self.0 -= 1;
+        Ok((self.0 == 0).then_some(POLLS))
+    }
+}
+
+#[async_trait]
+impl Waiter<u32, TimedOut> for Countdown {
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn default_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        Some(POLL_TIMEOUT)
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, TimedOut> {
+        self.step()
+    }
+
+    fn timeout_error(&self) -> TimedOut {
+        TimedOut
+    }
+}
+
+/// Same as `Countdown`, but implementing `NativeWaiter`.
+struct NativeCountdown(Countdown);
+
+impl NativeWaiter<u32, TimedOut> for NativeCountdown {
+    type Timer = DefaultTimer;
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn default_delay(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        Some(POLL_TIMEOUT)
+    }
+
+    fn timer(&self) -> &DefaultTimer {
+        &DefaultTimer
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, TimedOut> {
+        self.0.step()
+    }
+
+    fn timeout_error(&self) -> TimedOut {
+        TimedOut
+    }
+}
+
+/// Run `future` and report the number of allocations it made.
+fn measure<F>(name: &str, future: F)
+where
+    F: Future<Output = Result<u32, TimedOut>>,
+{
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .expect("failed to create a runtime");
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let result = runtime.block_on(future);
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    assert_eq!(result, Ok(POLLS));
+    println!(
+        "{:<12} {:>6} polls, {:>6} allocations, {:?}",
+        name, POLLS, allocations, elapsed
+    );
+}
+
+fn main() {
+    measure("async-trait", Countdown(POLLS).wait());
+    measure("native", NativeCountdown(Countdown(POLLS)).wait());
+    measure("boxed", NativeCountdown(Countdown(POLLS)).boxed().wait());
+}
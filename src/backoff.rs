@@ -0,0 +1,266 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Backoff strategies for delays between polls.
+
+use std::time::Duration;
+
+/// Strategy for computing delays between two attempts.
+///
+/// The waiting loops call `next_delay` once after every unsuccessful poll.
+pub trait Backoff {
+    /// Delay before the next attempt.
+    fn next_delay(&mut self) -> Duration;
+
+    /// Limit the delays produced by this strategy to `max`.
+    fn capped(self, max: Duration) -> Capped<Self>
+    where
+        Self: Sized,
+    {
+        Capped::new(self, max)
+    }
+
+    /// Randomize the delays produced by this strategy.
+    fn with_jitter(self, jitter: Jitter) -> Jittered<Self>
+    where
+        Self: Sized,
+    {
+        Jittered::new(self, jitter)
+    }
+}
+
+impl<B: Backoff + ?Sized> Backoff for &mut B {
+    fn next_delay(&mut self) -> Duration {
+        (**self).next_delay()
+    }
+}
+
+impl<B: Backoff + ?Sized> Backoff for Box<B> {
+    fn next_delay(&mut self) -> Duration {
+        (**self).next_delay()
+    }
+}
+
+/// The same delay between all attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Constant {
+    delay: Duration,
+}
+
+impl Constant {
+    /// Create a constant backoff.
+    pub fn new(delay: Duration) -> Constant {
+        Constant { delay }
+    }
+}
+
+impl Backoff for Constant {
+    fn next_delay(&mut self) -> Duration {
+        self.delay
+    }
+}
+
+/// Delay growing by a fixed step after every attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Linear {
+    current: Duration,
+    step: Duration,
+}
+
+impl Linear {
+    /// Create a linear backoff starting with `initial`.
+    pub fn new(initial: Duration, step: Duration) -> Linear {
+        Linear {
+            current: initial,
+            step,
+        }
+    }
+}
+
+impl Backoff for Linear {
+    fn next_delay(&mut self) -> Duration {
+        let result = self.current;
+        self.current = self.current.saturating_add(self.step);
+        result
+    }
+}
+
+/// Delay multiplied by a constant factor after every attempt.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Exponential {
+    current: Duration,
+    factor: f64,
+}
+
+impl Exponential {
+    /// Create an exponential backoff starting with `initial`.
+    ///
+    /// The delay is doubled after each attempt, use `with_factor` to change
+    /// that.
+    pub fn new(initial: Duration) -> Exponential {
+        Exponential {
+            current: initial,
+            factor: 2.0,
+        }
+    }
+
+    /// Use a different multiplication factor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is less than 1.0 or is not finite.
+    pub fn with_factor(mut self, factor: f64) -> Exponential {
+        assert!(
+            factor.is_finite() && factor >= 1.0,
+            "exponential backoff factor must be finite and at least 1.0"
+        );
+        self.factor = factor;
+        self
+    }
+}
+
+impl Backoff for Exponential {
+    fn next_delay(&mut self) -> Duration {
+        let result = self.current;
+        self.current = Duration::try_from_secs_f64(self.current.as_secs_f64() * self.factor)
+            .unwrap_or(Duration::MAX);
+        result
+    }
+}
+
+/// Delays following the Fibonacci sequence: `base`, `base`, `2 * base`,
+/// `3 * base`, `5 * base` and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fibonacci {
+    current: Duration,
+    next: Duration,
+}
+
+impl Fibonacci {
+    /// Create a Fibonacci backoff with the given base delay.
+    pub fn new(base: Duration) -> Fibonacci {
+        Fibonacci {
+            current: base,
+            next: base,
+        }
+    }
+}
+
+impl Backoff for Fibonacci {
+    fn next_delay(&mut self) -> Duration {
+        let result = self.current;
+        let next = self.current.saturating_add(self.next);
+        self.current = self.next;
+        self.next = next;
+        result
+    }
+}
+
+/// Another backoff with an upper limit on delays.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Capped<B> {
+    inner: B,
+    max: Duration,
+}
+
+impl<B> Capped<B> {
+    /// Limit delays produced by `inner` to `max`.
+    pub fn new(inner: B, max: Duration) -> Capped<B> {
+        Capped { inner, max }
+    }
+
+    /// Get the wrapped backoff back.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+}
+
+impl<B: Backoff> Backoff for Capped<B> {
+    fn next_delay(&mut self) -> Duration {
+        self.inner.next_delay().min(self.max)
+    }
+}
+
+/// Kind of randomization applied to delays.
+///
+/// See <https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/>
+/// for the detailed explanation of the modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Jitter {
+    /// A random delay between zero and the computed delay.
+    Full,
+    /// Half of the computed delay plus a random value up to the other half.
+    Equal,
+    /// A random delay between the computed delay and three times the
+    /// previous randomized delay, limited to `max`.
+    ///
+    /// The delays grow on their own, so it is usually combined with a
+    /// `Constant` backoff. The limit applies before the delay is used for
+    /// the next one, so the delays keep varying once it is reached.
+    Decorrelated {
+        /// Maximum delay.
+        max: Duration,
+    },
+}
+
+/// Another backoff with a random jitter applied to its delays.
+#[derive(Debug, Clone)]
+pub struct Jittered<B> {
+    inner: B,
+    jitter: Jitter,
+    rng: fastrand::Rng,
+    previous: Option<Duration>,
+}
+
+impl<B> Jittered<B> {
+    /// Apply `jitter` to delays produced by `inner`.
+    pub fn new(inner: B, jitter: Jitter) -> Jittered<B> {
+        Jittered {
+            inner,
+            jitter,
+            rng: fastrand::Rng::new(),
+            previous: None,
+        }
+    }
+
+    /// Use a random generator with a fixed seed.
+    ///
+    /// The same seed always produces the same sequence of delays.
+    pub fn with_seed(mut self, seed: u64) -> Jittered<B> {
+        self.rng = fastrand::Rng::with_seed(seed);
+        self
+    }
+
+    /// Get the wrapped backoff back.
+    pub fn into_inner(self) -> B {
+        self.inner
+    }
+
+    fn random_between(&mut self, low: Duration, high: Duration) -> Duration {
+        let low = u64::try_from(low.as_nanos()).unwrap_or(u64::MAX);
+        let high = u64::try_from(high.as_nanos()).unwrap_or(u64::MAX);
+        Duration::from_nanos(self.rng.u64(low..=high.max(low)))
+    }
+}
+
+impl<B: Backoff> Backoff for Jittered<B> {
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.inner.next_delay();
+        let result = match self.jitter {
+            Jitter::Full => self.random_between(Duration::ZERO, delay),
+            Jitter::Equal => {
+                let half = delay / 2;
+                half + self.random_between(Duration::ZERO, delay - half)
+            }
+            Jitter::Decorrelated { max } => {
+                let high = self.previous.unwrap_or(delay).saturating_mul(3);
+                self.random_between(delay, high).min(max)
+            }
+        };
+        self.previous = Some(result);
+        result
+    }
+}
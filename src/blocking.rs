@@ -0,0 +1,633 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Synchronous waiters that do not need an async runtime.
+//!
+//! `BlockingWaiter` mirrors `Waiter` with a synchronous `poll` and sleeps
+//! using `std::thread::sleep`. Use `Blocking` to wait for an async `Waiter`
+//! from synchronous code and `Async` to use a `BlockingWaiter` where a
+//! `Waiter` is expected.
+
+use std::any::type_name;
+use std::borrow::Cow;
+use std::future::{ready, Future};
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use crate::backoff::Constant;
+use crate::observer::{Event, Observed, Observer, Progress};
+use crate::wait_loop::{wait_loop, wait_simple, Limits, LoopWaiter};
+use crate::{Backoff, WaitError, WaitOptions, Waiter, WaiterCurrentState};
+
+/// Trait representing a waiter for some synchronous action to finish.
+///
+/// The type `T` is the final type of the action, `E` is an error.
+pub trait BlockingWaiter<T, E> {
+    /// Name of this waiter.
+    ///
+    /// Defaults to the name of the type.
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(type_name::<Self>())
+    }
+
+    /// Default timeout for this action.
+    ///
+    /// This timeout is used in the `wait` method.
+    /// If `None, wait forever by default.
+    fn default_wait_timeout(&self) -> Option<Duration>;
+
+    /// Default delay between two retries.
+    fn default_delay(&self) -> Duration;
+
+    /// Default delay before the first attempt.
+    ///
+    /// If `None` (the default), the action is polled right away.
+    fn default_initial_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Default maximum number of attempts.
+    ///
+    /// If `None` (the default), only the timeout is taken into account.
+    fn default_max_attempts(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the error returned by `poll` is transient.
+    ///
+    /// The default implementation treats all errors as fatal.
+    fn is_transient(&self, _err: &E) -> bool {
+        false
+    }
+
+    /// Default maximum number of consecutive transient errors.
+    ///
+    /// If `None` (the default), transient errors are retried until the
+    /// timeout or the maximum number of attempts is reached.
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        None
+    }
+
+    /// Update the current state of the action.
+    ///
+    /// Returns `T` if the action is finished, `None` if it is not. All errors
+    /// are propagated via the `Result`.
+    ///
+    /// This method should not be called again after it returned the final
+    /// result.
+    fn poll(&mut self) -> Result<Option<T>, E>;
+
+    /// Error to return on timeout.
+    fn timeout_error(&self) -> E;
+
+    /// Called by the waiting loops on every event.
+    ///
+    /// Used to notify observers, see `with_observer`. Does nothing by
+    /// default.
+    fn on_wait_event(&mut self, _event: Event<'_, T, E>, _progress: &Progress) {}
+
+    /// Attach an observer to this waiter.
+    ///
+    /// The observer is notified of all events in any of the waiting methods.
+    fn with_observer<O>(self, observer: O) -> Observed<Self, O>
+    where
+        Self: Sized,
+        O: Observer<T, E>,
+    {
+        Observed::new(self, observer, |_| None)
+    }
+
+    /// Attach an observer that has access to the current state.
+    fn with_state_observer<S, O>(self, observer: O) -> Observed<Self, O, S>
+    where
+        Self: Sized + WaiterCurrentState<S>,
+        O: Observer<T, E, S>,
+    {
+        Observed::new(self, observer, |waiter| Some(waiter.waiter_current_state()))
+    }
+
+    /// Turn this waiter into an async `Waiter`.
+    fn into_async(self) -> Async<Self>
+    where
+        Self: Sized,
+    {
+        Async::new(self)
+    }
+
+    /// Wait for the default amount of time.
+    fn wait(self) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_with_backoff(Constant::new(delay))
+    }
+
+    /// Wait for specified amount of time.
+    fn wait_for(self, duration: Duration) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_for_with_delay(duration, delay)
+    }
+
+    /// Wait for specified amount of time.
+    fn wait_for_with_delay(self, duration: Duration, delay: Duration) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        self.wait_for_with_backoff(duration, Constant::new(delay))
+    }
+
+    /// Wait for the default amount of time using the given backoff.
+    fn wait_with_backoff<B>(self, backoff: B) -> Result<T, E>
+    where
+        Self: Sized,
+        B: Backoff,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::new(&waiter, waiter.0.default_wait_timeout());
+        block_on(wait_simple(&mut waiter, limits, backoff))
+    }
+
+    /// Wait for specified amount of time using the given backoff.
+    fn wait_for_with_backoff<B>(self, duration: Duration, backoff: B) -> Result<T, E>
+    where
+        Self: Sized,
+        B: Backoff,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::new(&waiter, Some(duration));
+        block_on(wait_simple(&mut waiter, limits, backoff))
+    }
+
+    /// Wait until the given deadline.
+    ///
+    /// No polls are made after the deadline, and the last delay is shortened
+    /// so that the timeout is reported as soon as the deadline is reached.
+    fn wait_until(self, deadline: Instant) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_until_with_delay(deadline, delay)
+    }
+
+    /// Wait until the given deadline with given delay between attempts.
+    fn wait_until_with_delay(self, deadline: Instant, delay: Duration) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::until(&waiter, Some(deadline));
+        block_on(wait_simple(&mut waiter, limits, Constant::new(delay)))
+    }
+
+    /// Wait forever.
+    fn wait_forever(self) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_forever_with_delay(delay)
+    }
+
+    /// Wait forever with given delay between attempts.
+    fn wait_forever_with_delay(self, delay: Duration) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::new(&waiter, None);
+        block_on(wait_simple(&mut waiter, limits, Constant::new(delay)))
+    }
+
+    /// Wait with the given options.
+    ///
+    /// Options that are not set are taken from this waiter, e.g.
+    /// `WaitOptions::new()` waits exactly like `wait_detailed`. A cancelled
+    /// token is noticed before the next delay or `poll` call, since neither
+    /// can be interrupted. Poll timeouts are not enforced.
+    fn wait_with(self, options: WaitOptions) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+    {
+        let mut waiter = Driven(self);
+        let options = options.resolve(&waiter);
+        block_on(wait_loop(
+            &mut waiter,
+            options.limits,
+            options.backoff,
+            |_| None,
+            options.cancellation_token.as_ref(),
+        ))
+    }
+
+    /// Wait for the default amount of time, reporting details on failure.
+    fn wait_detailed(self) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+    {
+        let duration = self.default_wait_timeout();
+        let delay = self.default_delay();
+        self.wait_with_backoff_detailed(duration, Constant::new(delay))
+    }
+
+    /// Wait for specified amount of time, reporting details on failure.
+    fn wait_for_detailed(self, duration: Duration) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_with_backoff_detailed(Some(duration), Constant::new(delay))
+    }
+
+    /// Wait for at most the given number of attempts.
+    ///
+    /// At least one attempt is always made. The default timeout is ignored,
+    /// `WaitError::AttemptsExhausted` is returned when the limit is reached.
+    fn wait_attempts(self, attempts: u32) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits {
+            max_attempts: Some(attempts),
+            ..Limits::new(&waiter, None)
+        };
+        let backoff = Constant::new(waiter.0.default_delay());
+        block_on(wait_loop(&mut waiter, limits, backoff, |_| None, None))
+    }
+
+    /// Wait for specified amount of time or number of attempts.
+    ///
+    /// Stops on whichever limit is reached first: `WaitError::Timeout` or
+    /// `WaitError::AttemptsExhausted` is returned accordingly.
+    fn wait_for_attempts(self, duration: Duration, attempts: u32) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits {
+            max_attempts: Some(attempts),
+            ..Limits::new(&waiter, Some(duration))
+        };
+        let backoff = Constant::new(waiter.0.default_delay());
+        block_on(wait_loop(&mut waiter, limits, backoff, |_| None, None))
+    }
+
+    /// Wait using the given backoff, reporting details on failure.
+    ///
+    /// If `duration` is `None`, wait forever.
+    fn wait_with_backoff_detailed<B>(
+        self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        B: Backoff,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::new(&waiter, duration);
+        block_on(wait_loop(&mut waiter, limits, backoff, |_| None, None))
+    }
+}
+
+/// Blocking waiting with details about the current state on failure.
+///
+/// Implemented for all blocking waiters that implement `WaiterCurrentState`,
+/// see `WaiterWithState`.
+pub trait BlockingWaiterWithState<T, E, S>: BlockingWaiter<T, E> + WaiterCurrentState<S> {
+    /// Wait for the default amount of time, reporting the last state on
+    /// failure.
+    fn wait_with_state(self) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized;
+
+    /// Wait for specified amount of time, reporting the last state on failure.
+    fn wait_for_with_state(self, duration: Duration) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized;
+
+    /// Wait using the given backoff, reporting the last state on failure.
+    ///
+    /// If `duration` is `None`, wait forever.
+    fn wait_with_backoff_and_state<B>(
+        self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized,
+        B: Backoff;
+}
+
+impl<W, T, E, S> BlockingWaiterWithState<T, E, S> for W
+where
+    W: BlockingWaiter<T, E> + WaiterCurrentState<S>,
+    S: Clone,
+{
+    fn wait_with_state(self) -> Result<T, WaitError<E, S>> {
+        let duration = self.default_wait_timeout();
+        let delay = self.default_delay();
+        self.wait_with_backoff_and_state(duration, Constant::new(delay))
+    }
+
+    fn wait_for_with_state(self, duration: Duration) -> Result<T, WaitError<E, S>> {
+        let delay = self.default_delay();
+        self.wait_with_backoff_and_state(Some(duration), Constant::new(delay))
+    }
+
+    fn wait_with_backoff_and_state<B>(
+        self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E, S>>
+    where
+        B: Backoff,
+    {
+        let mut waiter = Driven(self);
+        let limits = Limits::new(&waiter, duration);
+        let current_state = |waiter: &Driven<W>| Some(waiter.0.waiter_current_state().clone());
+        block_on(wait_loop(&mut waiter, limits, backoff, current_state, None))
+    }
+}
+
+/// Blocking waiter driven by the shared waiting loop.
+///
+/// `poll` and the delays block the current thread, so the loop always runs
+/// to completion in a single `block_on` call.
+struct Driven<W>(W);
+
+/// Marker for the implementation of `LoopWaiter` for `Driven`.
+enum ViaBlocking {}
+
+impl<W, T, E> LoopWaiter<T, E, ViaBlocking> for Driven<W>
+where
+    W: BlockingWaiter<T, E>,
+{
+    type Sleep = ThreadSleep;
+
+    fn name(&self) -> Cow<'static, str> {
+        self.0.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.0.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.0.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.0.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.0.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.0.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.0.default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        // A synchronous `poll` cannot be interrupted.
+        None
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.0.timeout_error()
+    }
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> ThreadSleep {
+        ThreadSleep(duration)
+    }
+
+    fn on_cancel(&mut self) {}
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        self.0.on_wait_event(event, progress)
+    }
+
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>> {
+        ready(self.0.poll())
+    }
+
+    fn timeout_error(&self) -> E {
+        self.0.timeout_error()
+    }
+}
+
+/// Future sleeping with `std::thread::sleep` when polled.
+struct ThreadSleep(Duration);
+
+impl Future for ThreadSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        thread::sleep(self.0);
+        Poll::Ready(())
+    }
+}
+
+/// Async `Waiter` used as a `BlockingWaiter`.
+///
+/// Every `poll` call blocks the current thread until the future finishes.
+/// No async runtime is started, so the waiter must not rely on one (e.g. on
+/// tokio I/O or timers). `Waiter::default_poll_timeout` is not enforced.
+#[derive(Debug, Clone)]
+pub struct Blocking<W> {
+    inner: W,
+}
+
+impl<W> Blocking<W> {
+    /// Wrap an async waiter.
+    pub fn new(inner: W) -> Blocking<W> {
+        Blocking { inner }
+    }
+
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W, T, E> BlockingWaiter<T, E> for Blocking<W>
+where
+    W: Waiter<T, E>,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.inner.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.inner.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.inner.default_max_transient_failures()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        self.inner.on_wait_event(event, progress)
+    }
+
+    fn poll(&mut self) -> Result<Option<T>, E> {
+        block_on(self.inner.poll())
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+impl<W, X> WaiterCurrentState<X> for Blocking<W>
+where
+    W: WaiterCurrentState<X>,
+{
+    fn waiter_current_state(&self) -> &X {
+        self.inner.waiter_current_state()
+    }
+}
+
+/// `BlockingWaiter` used as an async `Waiter`.
+///
+/// The synchronous `poll` runs directly in the async task, blocking the
+/// executor thread for its duration.
+///
+/// Implements `Waiter` only if one of the timer features is enabled.
+#[derive(Debug, Clone)]
+pub struct Async<W> {
+    inner: W,
+}
+
+impl<W> Async<W> {
+    /// Wrap a blocking waiter.
+    pub fn new(inner: W) -> Async<W> {
+        Async { inner }
+    }
+
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+with_default_timer! {
+    use async_trait::async_trait;
+
+    #[async_trait]
+    impl<W, T, E> Waiter<T, E> for Async<W>
+    where
+        W: BlockingWaiter<T, E> + Send,
+    {
+        fn name(&self) -> Cow<'static, str> {
+            self.inner.name()
+        }
+
+        fn default_wait_timeout(&self) -> Option<Duration> {
+            self.inner.default_wait_timeout()
+        }
+
+        fn default_delay(&self) -> Duration {
+            self.inner.default_delay()
+        }
+
+        fn default_initial_delay(&self) -> Option<Duration> {
+            self.inner.default_initial_delay()
+        }
+
+        fn default_max_attempts(&self) -> Option<u32> {
+            self.inner.default_max_attempts()
+        }
+
+        fn is_transient(&self, err: &E) -> bool {
+            self.inner.is_transient(err)
+        }
+
+        fn default_max_transient_failures(&self) -> Option<u32> {
+            self.inner.default_max_transient_failures()
+        }
+
+        fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+            self.inner.on_wait_event(event, progress)
+        }
+
+        async fn poll(&mut self) -> Result<Option<T>, E> {
+            self.inner.poll()
+        }
+
+        fn timeout_error(&self) -> E {
+            self.inner.timeout_error()
+        }
+    }
+}
+
+impl<W, X> WaiterCurrentState<X> for Async<W>
+where
+    W: WaiterCurrentState<X>,
+{
+    fn waiter_current_state(&self) -> &X {
+        self.inner.waiter_current_state()
+    }
+}
+
+/// Waker unparking the thread that waits for a future.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` to completion on the current thread.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
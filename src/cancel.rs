@@ -0,0 +1,142 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Cancellation of in-flight waits.
+
+use std::collections::HashMap;
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+/// Token to cancel waiting from another task.
+///
+/// Clones of the token share the same state: cancelling one of them cancels
+/// all waits using any of the clones.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    wakers: Mutex<Wakers>,
+}
+
+/// Wakers of pending `WaitForCancellation` futures.
+///
+/// Every future gets its own key, so that it can deregister on drop.
+#[derive(Debug, Default)]
+struct Wakers {
+    next_key: u64,
+    wakers: HashMap<u64, Waker>,
+}
+
+impl CancellationToken {
+    /// Create a new token.
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Cancel all waits using this token.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        let wakers = std::mem::take(&mut self.inner.wakers().wakers);
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Future that resolves when the token is cancelled.
+    pub fn cancelled(&self) -> WaitForCancellation<'_> {
+        WaitForCancellation {
+            token: self,
+            key: None,
+        }
+    }
+}
+
+impl Inner {
+    fn wakers(&self) -> MutexGuard<'_, Wakers> {
+        self.wakers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Future returned by `CancellationToken::cancelled`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct WaitForCancellation<'a> {
+    token: &'a CancellationToken,
+    key: Option<u64>,
+}
+
+impl Future for WaitForCancellation<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        if this.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = this.token.inner.wakers();
+        // Re-check under the lock, `cancel` may have drained the list already.
+        if this.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+        match this.key.and_then(|key| wakers.wakers.get_mut(&key)) {
+            Some(waker) => {
+                if !waker.will_wake(cx.waker()) {
+                    waker.clone_from(cx.waker());
+                }
+            }
+            None => {
+                let key = wakers.next_key;
+                wakers.next_key += 1;
+                wakers.wakers.insert(key, cx.waker().clone());
+                this.key = Some(key);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for WaitForCancellation<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.inner.wakers().wakers.remove(&key);
+        }
+    }
+}
+
+/// Run `future` unless `token` is cancelled first.
+///
+/// Returns `None` on cancellation.
+pub(crate) async fn or_cancelled<F: Future>(
+    token: Option<&CancellationToken>,
+    future: F,
+) -> Option<F::Output> {
+    let Some(token) = token else {
+        return Some(future.await);
+    };
+
+    let mut future = pin!(future);
+    let mut cancelled = pin!(token.cancelled());
+    poll_fn(|cx| {
+        if cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        future.as_mut().poll(cx).map(Some)
+    })
+    .await
+}
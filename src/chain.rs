@@ -0,0 +1,185 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for several waiters in sequence.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use crate::backoff::Constant;
+use crate::options::TimeLimit;
+use crate::wait_loop::{wait_loop, Limits};
+use crate::{WaitError, Waiter};
+
+/// Result of the chain so far and the time limit for the remaining stages.
+type ChainFuture<T, E> =
+    Pin<Box<dyn Future<Output = Result<(T, Option<TimeLimit>), ChainError<E>>> + Send>>;
+
+/// Run the chain with the given time limit, `None` to use defaults of waiters.
+type Run<T, E> = Box<dyn FnOnce(Option<TimeLimit>) -> ChainFuture<T, E> + Send>;
+
+/// Waiters run one after another.
+///
+/// Each next waiter is created from the result of the previous one. All
+/// waiters share one time limit, set with `with_timeout` or `with_deadline`;
+/// without it, each waiter uses its own default timeout.
+///
+/// Created by `WaitChain::new` or `WaiterExt::then`.
+pub struct WaitChain<T, E> {
+    run: Run<T, E>,
+    stages: usize,
+    time_limit: Option<TimeLimit>,
+}
+
+impl<T, E> WaitChain<T, E>
+where
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    /// Start a chain with `waiter` as its first stage.
+    pub fn new<W>(waiter: W) -> WaitChain<T, E>
+    where
+        W: Waiter<T, E> + Send + 'static,
+    {
+        WaitChain {
+            run: Box::new(move |time_limit| Box::pin(run_stage(waiter, time_limit, 0))),
+            stages: 1,
+            time_limit: None,
+        }
+    }
+
+    /// Add a stage created by `f` from the result of the previous stage.
+    pub fn then<U, W, F>(self, f: F) -> WaitChain<U, E>
+    where
+        U: Send + 'static,
+        W: Waiter<U, E> + Send + 'static,
+        F: FnOnce(T) -> W + Send + 'static,
+    {
+        let previous = self.run;
+        let stage = self.stages;
+        WaitChain {
+            run: Box::new(move |time_limit| {
+                Box::pin(async move {
+                    let (result, time_limit) = previous(time_limit).await?;
+                    run_stage(f(result), time_limit, stage).await
+                })
+            }),
+            stages: stage + 1,
+            time_limit: self.time_limit,
+        }
+    }
+
+    /// Wait for the specified amount of time for the whole chain.
+    ///
+    /// Overrides `with_deadline`.
+    pub fn with_timeout(mut self, timeout: Duration) -> WaitChain<T, E> {
+        self.time_limit = Some(TimeLimit::Timeout(timeout));
+        self
+    }
+
+    /// Wait until the given deadline for the whole chain.
+    ///
+    /// Overrides `with_timeout`.
+    pub fn with_deadline(mut self, deadline: Instant) -> WaitChain<T, E> {
+        self.time_limit = Some(TimeLimit::Deadline(deadline));
+        self
+    }
+
+    /// Run all stages and return the result of the last one.
+    pub async fn wait(self) -> Result<T, ChainError<E>> {
+        let (result, _) = (self.run)(self.time_limit).await?;
+        Ok(result)
+    }
+}
+
+impl<T, E> fmt::Debug for WaitChain<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitChain")
+            .field("stages", &self.stages)
+            .field("time_limit", &self.time_limit)
+            .finish_non_exhaustive()
+    }
+}
+
+async fn run_stage<W, T, E>(
+    mut waiter: W,
+    time_limit: Option<TimeLimit>,
+    stage: usize,
+) -> Result<(T, Option<TimeLimit>), ChainError<E>>
+where
+    W: Waiter<T, E>,
+{
+    let deadline = time_limit.map(|time_limit| time_limit.deadline(waiter.timer().now()));
+    let limits = match deadline {
+        Some(deadline) => Limits::until(&waiter, deadline),
+        None => Limits::new(&waiter, waiter.default_wait_timeout()),
+    };
+    let backoff = Constant::new(waiter.default_delay());
+    // The following stages wait until the same deadline.
+    let time_limit =
+        deadline.map(|deadline| deadline.map_or(TimeLimit::Forever, TimeLimit::Deadline));
+    wait_loop(&mut waiter, limits, backoff, |_| None, None)
+        .await
+        .map(|result| (result, time_limit))
+        .map_err(|error| ChainError {
+            stage,
+            name: waiter.name(),
+            error,
+        })
+}
+
+/// Error of a `WaitChain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainError<E> {
+    stage: usize,
+    name: Cow<'static, str>,
+    error: WaitError<E>,
+}
+
+impl<E> ChainError<E> {
+    /// Index of the failed stage, starting with zero.
+    pub fn stage(&self) -> usize {
+        self.stage
+    }
+
+    /// Name of the waiter of the failed stage.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Error of the failed stage.
+    pub fn error(&self) -> &WaitError<E> {
+        &self.error
+    }
+
+    /// Get the error of the failed stage.
+    pub fn into_error(self) -> WaitError<E> {
+        self.error
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for ChainError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stage {} ({}) failed: {}",
+            self.stage, self.name, self.error
+        )
+    }
+}
+
+impl<E> Error for ChainError<E>
+where
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -0,0 +1,375 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Combinators transforming results of waiters.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::observer::{Event, Progress};
+use crate::{DynTimer, WaitChain, Waiter, WaiterCurrentState};
+
+/// Delegate the configuration hooks that do not depend on the error type.
+macro_rules! delegate_defaults {
+    () => {
+        fn name(&self) -> Cow<'static, str> {
+            self.inner.name()
+        }
+
+        fn default_wait_timeout(&self) -> Option<Duration> {
+            self.inner.default_wait_timeout()
+        }
+
+        fn default_delay(&self) -> Duration {
+            self.inner.default_delay()
+        }
+
+        fn default_initial_delay(&self) -> Option<Duration> {
+            self.inner.default_initial_delay()
+        }
+
+        fn default_max_attempts(&self) -> Option<u32> {
+            self.inner.default_max_attempts()
+        }
+
+        fn default_max_transient_failures(&self) -> Option<u32> {
+            self.inner.default_max_transient_failures()
+        }
+
+        fn default_poll_timeout(&self) -> Option<Duration> {
+            self.inner.default_poll_timeout()
+        }
+
+        fn timer(&self) -> &dyn DynTimer {
+            self.inner.timer()
+        }
+
+        fn on_cancel(&mut self) {
+            self.inner.on_cancel()
+        }
+    };
+}
+
+/// Extension methods for all waiters.
+///
+/// The combinators do not pass events carrying the transformed result or
+/// error to `on_wait_event` of the wrapped waiter, so observers that need
+/// them have to be attached last, e.g. `waiter.map(f).with_observer(o)`.
+pub trait WaiterExt<T, E>: Waiter<T, E> {
+    /// Transform the result of waiting with `f`.
+    fn map<U, F>(self, f: F) -> Map<Self, F, T>
+    where
+        Self: Sized,
+        F: FnMut(T) -> U,
+    {
+        Map {
+            inner: self,
+            f,
+            _result: PhantomData,
+        }
+    }
+
+    /// Transform all errors of the waiter with `f`.
+    ///
+    /// Errors stay transient if the wrapped waiter considers the original
+    /// errors transient, use `map_err_with` to classify the transformed
+    /// errors instead.
+    fn map_err<E2, F>(self, f: F) -> MapErr<Self, F, E, fn(&E2) -> bool>
+    where
+        Self: Sized,
+        F: Fn(E) -> E2,
+    {
+        MapErr {
+            inner: self,
+            f,
+            is_transient: None,
+            inner_transient: None,
+            _error: PhantomData,
+        }
+    }
+
+    /// Transform all errors of the waiter with `f`, classifying the results
+    /// with `is_transient`.
+    ///
+    /// `is_transient` replaces `Waiter::is_transient` of the wrapped waiter
+    /// and is called with the transformed errors.
+    fn map_err_with<E2, F, C>(self, f: F, is_transient: C) -> MapErr<Self, F, E, C>
+    where
+        Self: Sized,
+        F: Fn(E) -> E2,
+        C: Fn(&E2) -> bool,
+    {
+        MapErr {
+            inner: self,
+            f,
+            is_transient: Some(is_transient),
+            inner_transient: None,
+            _error: PhantomData,
+        }
+    }
+
+    /// Transform the result of waiting with a fallible `f`.
+    ///
+    /// An error returned by `f` fails waiting, it is never considered
+    /// transient.
+    fn and_then<U, F>(self, f: F) -> AndThen<Self, F, T>
+    where
+        Self: Sized,
+        F: FnMut(T) -> Result<U, E>,
+    {
+        AndThen {
+            inner: self,
+            f,
+            finished: false,
+            _result: PhantomData,
+        }
+    }
+
+    /// Wait for this waiter, then for the waiter created by `f` from its
+    /// result.
+    ///
+    /// Further stages can be added with `WaitChain::then`.
+    fn then<U, W, F>(self, f: F) -> WaitChain<U, E>
+    where
+        Self: Sized + Send + 'static,
+        T: Send + 'static,
+        E: Send + 'static,
+        U: Send + 'static,
+        W: Waiter<U, E> + Send + 'static,
+        F: FnOnce(T) -> W + Send + 'static,
+    {
+        WaitChain::new(self).then(f)
+    }
+}
+
+impl<W, T, E> WaiterExt<T, E> for W where W: Waiter<T, E> + ?Sized {}
+
+/// Waiter with a transformed result.
+///
+/// Created by `WaiterExt::map`. Events carrying the transformed result are
+/// not passed to `on_wait_event` of the wrapped waiter.
+#[derive(Debug, Clone)]
+pub struct Map<W, F, T> {
+    inner: W,
+    f: F,
+    _result: PhantomData<fn(T)>,
+}
+
+impl<W, F, T> Map<W, F, T> {
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<W, F, T, U, E> Waiter<U, E> for Map<W, F, T>
+where
+    W: Waiter<T, E> + Send,
+    F: FnMut(T) -> U + Send,
+{
+    delegate_defaults!();
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.inner.poll_timeout_error()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, U, E>, progress: &Progress) {
+        if let Some(event) = without_result(event) {
+            self.inner.on_wait_event(event, progress)
+        }
+    }
+
+    async fn poll(&mut self) -> Result<Option<U>, E> {
+        Ok(self.inner.poll().await?.map(&mut self.f))
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+/// Waiter with transformed errors.
+///
+/// Created by `WaiterExt::map_err` and `WaiterExt::map_err_with`. Events
+/// carrying a transformed error are not passed to `on_wait_event` of the
+/// wrapped waiter.
+#[derive(Debug, Clone)]
+pub struct MapErr<W, F, E, C> {
+    inner: W,
+    f: F,
+    /// Classification of the transformed errors, `None` to keep the one of
+    /// the wrapped waiter.
+    is_transient: Option<C>,
+    /// Whether the wrapped waiter considers the error of the last `poll`
+    /// call transient, `None` if the call has not returned.
+    inner_transient: Option<bool>,
+    _error: PhantomData<fn(E)>,
+}
+
+impl<W, F, E, C> MapErr<W, F, E, C> {
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<W, F, C, T, E, E2> Waiter<T, E2> for MapErr<W, F, E, C>
+where
+    W: Waiter<T, E> + Send,
+    F: Fn(E) -> E2 + Send,
+    C: Fn(&E2) -> bool + Send,
+{
+    delegate_defaults!();
+
+    fn is_transient(&self, err: &E2) -> bool {
+        match (&self.is_transient, self.inner_transient) {
+            (Some(is_transient), _) => is_transient(err),
+            (None, Some(inner_transient)) => inner_transient,
+            // The last `poll` call has timed out.
+            (None, None) => {
+                let err = self.inner.poll_timeout_error();
+                self.inner.is_transient(&err)
+            }
+        }
+    }
+
+    fn poll_timeout_error(&self) -> E2 {
+        (self.f)(self.inner.poll_timeout_error())
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E2>, progress: &Progress) {
+        if let Some(event) = without_error(event) {
+            self.inner.on_wait_event(event, progress)
+        }
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E2> {
+        self.inner_transient = None;
+        let result = self.inner.poll().await;
+        if let Err(err) = &result {
+            self.inner_transient = Some(self.inner.is_transient(err));
+        }
+        result.map_err(&self.f)
+    }
+
+    fn timeout_error(&self) -> E2 {
+        (self.f)(self.inner.timeout_error())
+    }
+}
+
+/// Waiter with a fallible transformation of the result.
+///
+/// Created by `WaiterExt::and_then`. Events carrying the transformed result
+/// are not passed to `on_wait_event` of the wrapped waiter.
+#[derive(Debug, Clone)]
+pub struct AndThen<W, F, T> {
+    inner: W,
+    f: F,
+    /// Whether the wrapped waiter has returned its result.
+    finished: bool,
+    _result: PhantomData<fn(T)>,
+}
+
+impl<W, F, T> AndThen<W, F, T> {
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<W, F, T, U, E> Waiter<U, E> for AndThen<W, F, T>
+where
+    W: Waiter<T, E> + Send,
+    F: FnMut(T) -> Result<U, E> + Send,
+{
+    delegate_defaults!();
+
+    fn is_transient(&self, err: &E) -> bool {
+        // Errors of `f` must never be retried: the wrapped waiter cannot be
+        // polled again after returning its result.
+        !self.finished && self.inner.is_transient(err)
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.inner.poll_timeout_error()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, U, E>, progress: &Progress) {
+        if let Some(event) = without_result(event) {
+            self.inner.on_wait_event(event, progress)
+        }
+    }
+
+    async fn poll(&mut self) -> Result<Option<U>, E> {
+        match self.inner.poll().await? {
+            Some(result) => {
+                self.finished = true;
+                (self.f)(result).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+macro_rules! delegate_current_state {
+    ($($wrapper:ident<$($param:ident),+>),+) => {
+        $(
+            impl<W, $($param,)+ X> WaiterCurrentState<X> for $wrapper<W, $($param),+>
+            where
+                W: WaiterCurrentState<X>,
+            {
+                fn waiter_current_state(&self) -> &X {
+                    self.inner.waiter_current_state()
+                }
+            }
+        )+
+    };
+}
+
+delegate_current_state!(Map<F, P>, MapErr<F, P, C>, AndThen<F, P>);
+
+/// Convert an event for the wrapped waiter unless it carries a result.
+fn without_result<'a, T, U, E>(event: Event<'a, U, E>) -> Option<Event<'a, T, E>> {
+    Some(match event {
+        Event::Start => Event::Start,
+        Event::PollResult(Ok(None)) => Event::PollResult(Ok(None)),
+        Event::PollResult(Err(err)) => Event::PollResult(Err(err)),
+        Event::Sleep(delay) => Event::Sleep(delay),
+        Event::Timeout => Event::Timeout,
+        Event::AttemptsExhausted => Event::AttemptsExhausted,
+        Event::Error(err) => Event::Error(err),
+        Event::Cancelled => Event::Cancelled,
+        Event::PollResult(Ok(Some(_))) | Event::Success(_) => return None,
+    })
+}
+
+/// Convert an event for the wrapped waiter unless it carries an error.
+fn without_error<'a, T, E, E2>(event: Event<'a, T, E2>) -> Option<Event<'a, T, E>> {
+    Some(match event {
+        Event::Start => Event::Start,
+        Event::PollResult(Ok(result)) => Event::PollResult(Ok(result)),
+        Event::Sleep(delay) => Event::Sleep(delay),
+        Event::Success(result) => Event::Success(result),
+        Event::Timeout => Event::Timeout,
+        Event::AttemptsExhausted => Event::AttemptsExhausted,
+        Event::Cancelled => Event::Cancelled,
+        Event::PollResult(Err(_)) | Event::Error(_) => return None,
+    })
+}
@@ -0,0 +1,167 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structured errors of the waiting loops.
+
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+/// Simple timeout error.
+///
+/// Used by waiters created with `poll_fn` when no custom timeout error is
+/// provided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct TimedOut;
+
+impl fmt::Display for TimedOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("timed out waiting for the action to finish")
+    }
+}
+
+impl Error for TimedOut {}
+
+/// Reason why waiting did not succeed.
+///
+/// The type `E` is the error of the `Waiter`, `S` is the type of its current
+/// state (see `WaiterCurrentState`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitError<E, S = ()> {
+    /// The timeout was reached.
+    Timeout {
+        /// Time spent waiting.
+        elapsed: Duration,
+        /// Number of `poll` calls made.
+        attempts: u32,
+        /// State of the waiter as of the last `poll` call, if known.
+        last_state: Option<S>,
+        /// The last transient error returned by `poll`, if any.
+        last_error: Option<E>,
+    },
+    /// The `poll` call failed.
+    Poll(E),
+    /// The wait was cancelled.
+    Cancelled,
+    /// The maximum number of attempts was reached.
+    AttemptsExhausted {
+        /// Time spent waiting.
+        elapsed: Duration,
+        /// Number of `poll` calls made.
+        attempts: u32,
+        /// State of the waiter as of the last `poll` call, if known.
+        last_state: Option<S>,
+        /// The last transient error returned by `poll`, if any.
+        last_error: Option<E>,
+    },
+}
+
+impl<E, S> WaitError<E, S> {
+    /// Time spent waiting, if known.
+    pub fn elapsed(&self) -> Option<Duration> {
+        match self {
+            WaitError::Timeout { elapsed, .. } | WaitError::AttemptsExhausted { elapsed, .. } => {
+                Some(*elapsed)
+            }
+            _ => None,
+        }
+    }
+
+    /// Number of `poll` calls made, if known.
+    pub fn attempts(&self) -> Option<u32> {
+        match self {
+            WaitError::Timeout { attempts, .. } | WaitError::AttemptsExhausted { attempts, .. } => {
+                Some(*attempts)
+            }
+            _ => None,
+        }
+    }
+
+    /// State of the waiter as of the last `poll` call, if known.
+    pub fn last_state(&self) -> Option<&S> {
+        match self {
+            WaitError::Timeout { last_state, .. }
+            | WaitError::AttemptsExhausted { last_state, .. } => last_state.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the error is caused by reaching the timeout.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, WaitError::Timeout { .. })
+    }
+
+    /// The last transient error returned by `poll`, if any.
+    pub fn last_error(&self) -> Option<&E> {
+        match self {
+            WaitError::Timeout { last_error, .. }
+            | WaitError::AttemptsExhausted { last_error, .. } => last_error.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// Get the error returned by `poll`, if any.
+    ///
+    /// For timeouts and exhausted attempts, this is the last transient error.
+    pub fn into_poll_error(self) -> Option<E> {
+        match self {
+            WaitError::Poll(err) => Some(err),
+            WaitError::Timeout { last_error, .. }
+            | WaitError::AttemptsExhausted { last_error, .. } => last_error,
+            WaitError::Cancelled => None,
+        }
+    }
+}
+
+impl<E: fmt::Display, S> fmt::Display for WaitError<E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Timeout {
+                elapsed,
+                attempts,
+                last_error,
+                ..
+            } => {
+                write!(
+                    f,
+                    "timed out after {:?} and {} attempt(s)",
+                    elapsed, attempts
+                )?;
+                if let Some(err) = last_error {
+                    write!(f, ", last error: {}", err)?;
+                }
+                Ok(())
+            }
+            WaitError::Poll(err) => write!(f, "{}", err),
+            WaitError::Cancelled => f.write_str("waiting was cancelled"),
+            WaitError::AttemptsExhausted {
+                elapsed,
+                attempts,
+                last_error,
+                ..
+            } => {
+                write!(f, "gave up after {} attempt(s) and {:?}", attempts, elapsed)?;
+                if let Some(err) = last_error {
+                    write!(f, ", last error: {}", err)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<E, S> Error for WaitError<E, S>
+where
+    E: Error + 'static,
+    S: fmt::Debug,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WaitError::Poll(err) => Some(err),
+            _ => self.last_error().map(|err| err as &(dyn Error + 'static)),
+        }
+    }
+}
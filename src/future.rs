@@ -0,0 +1,148 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Using waiters as futures.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::Waiter;
+
+/// Future waiting for a waiter for its default amount of time.
+///
+/// Created by `Waiter::into_wait_future` or `WaitFuture::new`. Resolves to
+/// the same result as `Waiter::wait`.
+#[must_use = "futures do nothing unless awaited"]
+pub struct WaitFuture<T, E> {
+    inner: Pin<Box<dyn Future<Output = Result<T, E>> + Send>>,
+}
+
+impl<T, E> WaitFuture<T, E> {
+    /// Create a future waiting for `waiter`.
+    pub fn new<W>(waiter: W) -> WaitFuture<T, E>
+    where
+        W: Waiter<T, E> + Send + 'static,
+        T: 'static,
+        E: Send + 'static,
+    {
+        WaitFuture {
+            inner: waiter.wait(),
+        }
+    }
+}
+
+impl<T, E> Future for WaitFuture<T, E> {
+    type Output = Result<T, E>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, E>> {
+        self.inner.as_mut().poll(cx)
+    }
+}
+
+impl<T, E> fmt::Debug for WaitFuture<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitFuture").finish_non_exhaustive()
+    }
+}
+
+/// Implement `IntoFuture` for a waiter type, so that it can be awaited
+/// directly.
+///
+/// Awaiting the waiter is the same as calling `Waiter::wait`. The waiter and
+/// its result types must be `'static`.
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use async_trait::async_trait;
+/// use waiter::{TimedOut, Waiter};
+///
+/// struct ServerWaiter {
+///     id: u32,
+/// }
+///
+/// #[async_trait]
+/// impl Waiter<u32, TimedOut> for ServerWaiter {
+///     fn default_wait_timeout(&self) -> Option<Duration> {
+///         Some(Duration::from_secs(60))
+///     }
+///
+///     fn default_delay(&self) -> Duration {
+///         Duration::from_secs(1)
+///     }
+///
+///     async fn poll(&mut self) -> Result<Option<u32>, TimedOut> {
+///         // Fetch the server here.
+///         Ok(Some(self.id))
+///     }
+///
+///     fn timeout_error(&self) -> TimedOut {
+///         TimedOut
+///     }
+/// }
+///
+/// waiter::impl_into_future!(ServerWaiter => u32, TimedOut);
+///
+/// async fn create_server() -> Result<u32, TimedOut> {
+///     ServerWaiter { id: 42 }.await
+/// }
+/// ```
+#[macro_export]
+macro_rules! impl_into_future {
+    ($waiter:ty => $t:ty, $e:ty) => {
+        impl ::std::future::IntoFuture for $waiter {
+            type Output = ::std::result::Result<$t, $e>;
+            type IntoFuture = $crate::WaitFuture<$t, $e>;
+
+            fn into_future(self) -> Self::IntoFuture {
+                $crate::WaitFuture::new(self)
+            }
+        }
+    };
+}
+
+// `PollFn` and `StateWaiter` only exist with a default timer.
+with_default_timer! {
+    use std::future::IntoFuture;
+
+    use crate::poll_fn::{PollFn, TimeoutError};
+    use crate::state_waiter::{StateWaitError, StateWaiter};
+
+    impl<F, Fut, G, T, E> IntoFuture for PollFn<F, G>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<Option<T>, E>> + Send + 'static,
+        G: TimeoutError<E> + Send + 'static,
+        T: 'static,
+        E: Send + 'static,
+    {
+        type Output = Result<T, E>;
+        type IntoFuture = WaitFuture<T, E>;
+
+        fn into_future(self) -> WaitFuture<T, E> {
+            WaitFuture::new(self)
+        }
+    }
+
+    impl<F, Fut, P, Q, S, E> IntoFuture for StateWaiter<F, P, Q, S>
+    where
+        F: FnMut() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<S, E>> + Send + 'static,
+        P: Fn(&S) -> bool + Send + 'static,
+        Q: Fn(&S) -> bool + Send + 'static,
+        S: Clone + Send + 'static,
+        E: Send + 'static,
+    {
+        type Output = Result<S, StateWaitError<S, E>>;
+        type IntoFuture = WaitFuture<S, StateWaitError<S, E>>;
+
+        fn into_future(self) -> Self::IntoFuture {
+            WaitFuture::new(self)
+        }
+    }
+}
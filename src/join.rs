@@ -0,0 +1,150 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for several waiters concurrently.
+
+use std::future::{poll_fn, Future};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use crate::backoff::Constant;
+use crate::options::TimeLimit;
+use crate::wait_loop::{wait_loop, Limits};
+use crate::{CancellationToken, WaitError, Waiter};
+
+/// Wait for all `waiters` concurrently.
+///
+/// Each waiter is polled with its own default delay. Without
+/// `JoinAll::with_timeout` or `JoinAll::with_deadline`, each waiter also uses
+/// its own default timeout.
+pub fn join_all<I>(waiters: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+{
+    JoinAll {
+        waiters: waiters.into_iter().collect(),
+        time_limit: None,
+        fail_fast: false,
+    }
+}
+
+/// Several waiters waited for concurrently.
+///
+/// Created by `join_all`.
+#[derive(Debug, Clone)]
+pub struct JoinAll<W> {
+    waiters: Vec<W>,
+    time_limit: Option<TimeLimit>,
+    fail_fast: bool,
+}
+
+impl<W> JoinAll<W> {
+    /// Wait for all waiters for the specified amount of time in total.
+    ///
+    /// Overrides `with_deadline`.
+    pub fn with_timeout(mut self, timeout: Duration) -> JoinAll<W> {
+        self.time_limit = Some(TimeLimit::Timeout(timeout));
+        self
+    }
+
+    /// Wait for all waiters until the given deadline.
+    ///
+    /// Overrides `with_timeout`.
+    pub fn with_deadline(mut self, deadline: Instant) -> JoinAll<W> {
+        self.time_limit = Some(TimeLimit::Deadline(deadline));
+        self
+    }
+
+    /// Stop waiting for all waiters once any of them fails.
+    ///
+    /// Waiters that have not finished by then result in
+    /// `WaitError::Cancelled`.
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> JoinAll<W> {
+        self.fail_fast = fail_fast;
+        self
+    }
+
+    /// Wait for all waiters.
+    ///
+    /// Returns the results in the same order as the waiters. Finished waiters
+    /// are no longer polled.
+    pub async fn wait<T, E>(self) -> Vec<Result<T, WaitError<E>>>
+    where
+        W: Waiter<T, E>,
+    {
+        let mut waiters = self.waiters;
+        let fail_fast = self.fail_fast;
+        let (results, _) = drive(&mut waiters, self.time_limit, |result| {
+            fail_fast && result.is_err()
+        })
+        .await;
+        results
+    }
+}
+
+/// Wait for all `waiters` concurrently until they finish or `stop` is true.
+///
+/// Once `stop` returns true for a result, the remaining waiters are
+/// cancelled. Returns the results in the order of the waiters and the index
+/// of the waiter that caused the stop, if any.
+pub(crate) async fn drive<W, T, E, F>(
+    waiters: &mut [W],
+    time_limit: Option<TimeLimit>,
+    stop: F,
+) -> (Vec<Result<T, WaitError<E>>>, Option<usize>)
+where
+    W: Waiter<T, E>,
+    F: Fn(&Result<T, WaitError<E>>) -> bool,
+{
+    // All waiters share the deadline, computed with the timer of the first.
+    let now = waiters.first().map(|waiter| waiter.timer().now());
+    let deadline = time_limit
+        .zip(now)
+        .map(|(time_limit, now)| time_limit.deadline(now));
+    let token = CancellationToken::new();
+    let mut futures = waiters
+        .iter_mut()
+        .map(|waiter| {
+            let limits = match deadline {
+                Some(deadline) => Limits::until(&*waiter, deadline),
+                None => Limits::new(&*waiter, waiter.default_wait_timeout()),
+            };
+            let backoff = Constant::new(waiter.default_delay());
+            Box::pin(wait_loop(waiter, limits, backoff, |_| None, Some(&token)))
+        })
+        .collect::<Vec<_>>();
+
+    let mut results = futures.iter().map(|_| None).collect::<Vec<_>>();
+    let mut stopped_by = None;
+    poll_fn(|cx| {
+        let mut finished = true;
+        for (index, (future, result)) in futures.iter_mut().zip(&mut results).enumerate() {
+            if result.is_some() {
+                continue;
+            }
+            match future.as_mut().poll(cx) {
+                Poll::Ready(outcome) => {
+                    if stopped_by.is_none() && stop(&outcome) {
+                        stopped_by = Some(index);
+                        // Wakes up the other loops so that they finish.
+                        token.cancel();
+                    }
+                    *result = Some(outcome);
+                }
+                Poll::Pending => finished = false,
+            }
+        }
+        if finished {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+    drop(futures);
+
+    (results.into_iter().flatten().collect(), stopped_by)
+}
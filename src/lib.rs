@@ -9,15 +9,90 @@
 //! The `Waiter` thread represents some action that can be polled for, and
 //! that can also fail.
 
+use std::any::type_name;
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
 use async_trait::async_trait;
-use tokio::time::sleep;
-use tokio::time::{Duration, Instant};
+
+/// Items relying on `timer::DefaultTimer`, which only exists when one of the
+/// timer features is enabled.
+macro_rules! with_default_timer {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(
+                feature = "tokio",
+                feature = "async-std",
+                feature = "smol",
+                feature = "futures-timer"
+            ))]
+            $item
+        )*
+    };
+}
+
+pub mod backoff;
+pub mod blocking;
+mod cancel;
+mod chain;
+mod combinators;
+mod error;
+mod future;
+mod join;
+#[cfg(feature = "metrics")]
+mod metrics;
+pub mod native;
+pub mod observer;
+mod options;
+mod select;
+#[cfg(feature = "stream")]
+mod stream;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod timer;
+#[cfg(feature = "tracing")]
+mod trace;
+mod wait_loop;
+
+pub use backoff::Backoff;
+pub use cancel::{CancellationToken, WaitForCancellation};
+pub use chain::{ChainError, WaitChain};
+pub use combinators::{AndThen, Map, MapErr, WaiterExt};
+pub use error::{TimedOut, WaitError};
+pub use future::WaitFuture;
+pub use join::{join_all, JoinAll};
+pub use observer::{Observed, Observer};
+pub use options::WaitOptions;
+pub use select::{select_ok, AllFailed, SelectOk};
+#[cfg(feature = "stream")]
+pub use stream::{WaitEvent, WaitStream};
+pub use timer::{DynTimer, Timer};
+
+use backoff::Constant;
+use observer::{Event, Progress};
+with_default_timer! {
+    mod poll_fn;
+    mod state_waiter;
+
+    pub use poll_fn::{poll_fn, DefaultTimeoutError, PollFn, TimeoutError};
+    pub use state_waiter::{StateWaitError, StateWaiter};
+
+    use timer::DefaultTimer;
+}
+use wait_loop::{wait_loop, wait_simple, Limits};
 
 /// Trait representing a waiter for some asynchronous action to finish.
 ///
 /// The type `T` is the final type of the action, `E` is an error.
 #[async_trait]
 pub trait Waiter<T, E> {
+    /// Name of this waiter used in metrics and tracing.
+    ///
+    /// Defaults to the name of the type.
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(type_name::<Self>())
+    }
+
     /// Default timeout for this action.
     ///
     /// This timeout is used in the `wait` method.
@@ -27,6 +102,121 @@ pub trait Waiter<T, E> {
     /// Default delay between two retries.
     fn default_delay(&self) -> Duration;
 
+    /// Default delay before the first attempt.
+    ///
+    /// Used by all waiting methods, the delay counts against the timeout.
+    /// If `None` (the default), the action is polled right away.
+    fn default_initial_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Default maximum number of attempts.
+    ///
+    /// Used by all waiting methods that do not accept an explicit limit.
+    /// If `None` (the default), only the timeout is taken into account.
+    fn default_max_attempts(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the error returned by `poll` is transient.
+    ///
+    /// Transient errors do not abort waiting, the action is polled again
+    /// after the usual delay. The default implementation treats all errors as
+    /// fatal.
+    fn is_transient(&self, _err: &E) -> bool {
+        false
+    }
+
+    /// Default maximum number of consecutive transient errors.
+    ///
+    /// The error that exceeds this limit is returned as if it was fatal.
+    /// If `None` (the default), transient errors are retried until the
+    /// timeout or the maximum number of attempts is reached.
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        None
+    }
+
+    /// Default maximum duration of a single `poll` call.
+    ///
+    /// A `poll` call that takes longer is aborted and treated as if it
+    /// returned `poll_timeout_error`. When set, the call is also aborted
+    /// once the overall timeout is reached. If `None` (the default), `poll`
+    /// calls are never aborted.
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Error to use when a single `poll` call times out.
+    ///
+    /// The error is checked with `is_transient`, so a poll timeout can either
+    /// be retried or abort waiting. Defaults to `timeout_error`.
+    fn poll_timeout_error(&self) -> E {
+        self.timeout_error()
+    }
+
+    /// Timer used by the waiting loops.
+    ///
+    /// Defaults to `timer::DefaultTimer`, which depends on the enabled
+    /// features.
+    #[cfg(any(
+        feature = "tokio",
+        feature = "async-std",
+        feature = "smol",
+        feature = "futures-timer"
+    ))]
+    fn timer(&self) -> &dyn DynTimer {
+        &DefaultTimer
+    }
+
+    /// Timer used by the waiting loops.
+    ///
+    /// Without any timer feature there is no default timer, so waiters have
+    /// to provide their own, e.g. a `testing::VirtualClock`.
+    #[cfg(not(any(
+        feature = "tokio",
+        feature = "async-std",
+        feature = "smol",
+        feature = "futures-timer"
+    )))]
+    fn timer(&self) -> &dyn DynTimer;
+
+    /// Called when waiting is cancelled via a `CancellationToken`.
+    ///
+    /// Can be used to clean up or report the reason. Does nothing by default.
+    fn on_cancel(&mut self) {}
+
+    /// Called by the waiting loops on every event.
+    ///
+    /// Used to notify observers, see `with_observer`. Does nothing by
+    /// default.
+    fn on_wait_event(&mut self, _event: Event<'_, T, E>, _progress: &Progress) {}
+
+    /// Attach an observer to this waiter.
+    ///
+    /// The observer is notified of all events in any of the waiting methods.
+    /// Several observers can be attached by calling this method repeatedly or
+    /// by passing a `Vec` of them.
+    ///
+    /// Attach observers after the combinators of `WaiterExt`: these do not
+    /// forward events carrying a transformed result or error, so e.g.
+    /// `on_success` is not called for `waiter.with_observer(o).map(f)`.
+    fn with_observer<O>(self, observer: O) -> Observed<Self, O>
+    where
+        Self: Sized,
+        O: Observer<T, E>,
+    {
+        Observed::new(self, observer, |_| None)
+    }
+
+    /// Attach an observer that has access to the current state.
+    fn with_state_observer<S, O>(self, observer: O) -> Observed<Self, O, S>
+    where
+        Self: Sized + WaiterCurrentState<S>,
+        O: Observer<T, E, S>,
+    {
+        Observed::new(self, observer, |waiter| Some(waiter.waiter_current_state()))
+    }
+
     /// Update the current state of the action.
     ///
     /// Returns `T` if the action is finished, `None` if it is not. All errors
@@ -37,8 +227,39 @@ pub trait Waiter<T, E> {
     async fn poll(&mut self) -> Result<Option<T>, E>;
 
     /// Error to return on timeout.
+    ///
+    /// Also used when the maximum number of attempts is reached by methods
+    /// that do not return `WaitError`.
     fn timeout_error(&self) -> E;
 
+    /// Turn this waiter into a future waiting for the default amount of time.
+    ///
+    /// The future resolves to the same result as `wait`. Use
+    /// `impl_into_future!` to make a waiter type awaitable directly.
+    fn into_wait_future(self) -> WaitFuture<T, E>
+    where
+        Self: Sized + Send + 'static,
+        T: 'static,
+        E: Send + 'static,
+    {
+        WaitFuture::new(self)
+    }
+
+    /// Turn this waiter into a stream of events.
+    ///
+    /// The stream waits like `wait_detailed`, yielding the current state
+    /// after every unsuccessful attempt and the error of every failed one.
+    #[cfg(feature = "stream")]
+    fn into_stream<S>(self) -> WaitStream<T, E, S>
+    where
+        Self: Sized + WaiterCurrentState<S> + Send + 'static,
+        T: Send + 'static,
+        E: Clone + Send + 'static,
+        S: Clone + Send + 'static,
+    {
+        WaitStream::new(self)
+    }
+
     /// Wait for the default amount of time.
     ///
     /// Consumes the `Waiter`.
@@ -66,18 +287,77 @@ pub trait Waiter<T, E> {
     }
 
     /// Wait for specified amount of time.
-    async fn wait_for_with_delay(mut self, duration: Duration, delay: Duration) -> Result<T, E>
+    async fn wait_for_with_delay(self, duration: Duration, delay: Duration) -> Result<T, E>
     where
         Self: Sized,
     {
-        let start = Instant::now();
-        while Instant::now().duration_since(start) <= duration {
-            if let Some(result) = self.poll().await? {
-                return Ok(result);
-            };
-            sleep(delay).await;
+        self.wait_for_with_backoff(duration, Constant::new(delay))
+            .await
+    }
+
+    /// Wait for the default amount of time using the given backoff.
+    async fn wait_with_backoff<B>(self, backoff: B) -> Result<T, E>
+    where
+        Self: Sized,
+        B: Backoff + Send,
+    {
+        let duration = self.default_wait_timeout();
+        match duration {
+            Some(duration) => self.wait_for_with_backoff(duration, backoff).await,
+            None => self.wait_forever_with_backoff(backoff).await,
         }
-        Err(self.timeout_error())
+    }
+
+    /// Wait for specified amount of time using the given backoff.
+    ///
+    /// The backoff is consulted for the delay after each unsuccessful poll.
+    async fn wait_for_with_backoff<B>(mut self, duration: Duration, backoff: B) -> Result<T, E>
+    where
+        Self: Sized,
+        B: Backoff + Send,
+    {
+        let limits = Limits::new(&self, Some(duration));
+        wait_simple(&mut self, limits, backoff).await
+    }
+
+    /// Wait for specified amount of time, aborting `poll` calls that take
+    /// longer than `poll_timeout`.
+    async fn wait_for_with_poll_timeout(
+        mut self,
+        duration: Duration,
+        poll_timeout: Duration,
+    ) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let limits = Limits {
+            poll_timeout: Some(poll_timeout),
+            ..Limits::new(&self, Some(duration))
+        };
+        let backoff = Constant::new(self.default_delay());
+        wait_simple(&mut self, limits, backoff).await
+    }
+
+    /// Wait until the given deadline.
+    ///
+    /// No polls are made after the deadline, and the last delay is shortened
+    /// so that the timeout is reported as soon as the deadline is reached.
+    /// The deadline is compared with the current time of `timer`.
+    async fn wait_until(self, deadline: Instant) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_until_with_delay(deadline, delay).await
+    }
+
+    /// Wait until the given deadline with given delay between attempts.
+    async fn wait_until_with_delay(mut self, deadline: Instant, delay: Duration) -> Result<T, E>
+    where
+        Self: Sized,
+    {
+        let limits = Limits::until(&self, Some(deadline));
+        wait_simple(&mut self, limits, Constant::new(delay)).await
     }
 
     /// Wait forever.
@@ -90,16 +370,233 @@ pub trait Waiter<T, E> {
     }
 
     /// Wait forever with given delay between attempts.
-    async fn wait_forever_with_delay(mut self, delay: Duration) -> Result<T, E>
+    async fn wait_forever_with_delay(self, delay: Duration) -> Result<T, E>
     where
         Self: Sized,
     {
-        loop {
-            if let Some(result) = self.poll().await? {
-                return Ok(result);
-            };
-            sleep(delay).await;
-        }
+        self.wait_forever_with_backoff(Constant::new(delay)).await
+    }
+
+    /// Wait forever using the given backoff.
+    async fn wait_forever_with_backoff<B>(mut self, backoff: B) -> Result<T, E>
+    where
+        Self: Sized,
+        B: Backoff + Send,
+    {
+        let limits = Limits::new(&self, None);
+        wait_simple(&mut self, limits, backoff).await
+    }
+
+    /// Wait with the given options.
+    ///
+    /// Options that are not set are taken from this waiter, e.g.
+    /// `WaitOptions::new()` waits exactly like `wait_detailed`.
+    async fn wait_with(mut self, options: WaitOptions) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let options = options.resolve(&self);
+        wait_loop(
+            &mut self,
+            options.limits,
+            options.backoff,
+            |_| None,
+            options.cancellation_token.as_ref(),
+        )
+        .await
+    }
+
+    /// Wait for the default amount of time, reporting details on failure.
+    ///
+    /// Unlike `wait`, the timeout is reported as `WaitError::Timeout` with
+    /// the elapsed time and the number of attempts.
+    async fn wait_detailed(self) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let duration = self.default_wait_timeout();
+        let delay = self.default_delay();
+        self.wait_with_backoff_detailed(duration, Constant::new(delay))
+            .await
+    }
+
+    /// Wait for specified amount of time, reporting details on failure.
+    async fn wait_for_detailed(self, duration: Duration) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let delay = self.default_delay();
+        self.wait_with_backoff_detailed(Some(duration), Constant::new(delay))
+            .await
+    }
+
+    /// Wait for at most the given number of attempts.
+    ///
+    /// At least one attempt is always made. The default timeout is ignored,
+    /// `WaitError::AttemptsExhausted` is returned when the limit is reached.
+    async fn wait_attempts(mut self, attempts: u32) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let limits = Limits {
+            max_attempts: Some(attempts),
+            ..Limits::new(&self, None)
+        };
+        let backoff = Constant::new(self.default_delay());
+        wait_loop(&mut self, limits, backoff, |_| None, None).await
+    }
+
+    /// Wait for specified amount of time or number of attempts.
+    ///
+    /// Stops on whichever limit is reached first: `WaitError::Timeout` or
+    /// `WaitError::AttemptsExhausted` is returned accordingly.
+    async fn wait_for_attempts(
+        mut self,
+        duration: Duration,
+        attempts: u32,
+    ) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let limits = Limits {
+            max_attempts: Some(attempts),
+            ..Limits::new(&self, Some(duration))
+        };
+        let backoff = Constant::new(self.default_delay());
+        wait_loop(&mut self, limits, backoff, |_| None, None).await
+    }
+
+    /// Wait for the default amount of time unless cancelled.
+    ///
+    /// Returns `WaitError::Cancelled` as soon as `token` is cancelled, even
+    /// in the middle of a `poll` call or a delay. `on_cancel` is called
+    /// before returning.
+    async fn wait_cancellable(self, token: CancellationToken) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let duration = self.default_wait_timeout();
+        let delay = self.default_delay();
+        self.wait_with_backoff_cancellable(duration, Constant::new(delay), token)
+            .await
+    }
+
+    /// Wait for specified amount of time unless cancelled.
+    async fn wait_for_cancellable(
+        self,
+        duration: Duration,
+        token: CancellationToken,
+    ) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+    {
+        let delay = self.default_delay();
+        self.wait_with_backoff_cancellable(Some(duration), Constant::new(delay), token)
+            .await
+    }
+
+    /// Wait using the given backoff unless cancelled.
+    ///
+    /// If `duration` is `None`, wait forever or until cancelled.
+    async fn wait_with_backoff_cancellable<B>(
+        mut self,
+        duration: Option<Duration>,
+        backoff: B,
+        token: CancellationToken,
+    ) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+        B: Backoff + Send,
+    {
+        let limits = Limits::new(&self, duration);
+        wait_loop(&mut self, limits, backoff, |_| None, Some(&token)).await
+    }
+
+    /// Wait using the given backoff, reporting details on failure.
+    ///
+    /// If `duration` is `None`, wait forever.
+    async fn wait_with_backoff_detailed<B>(
+        mut self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E>>
+    where
+        Self: Sized,
+        E: Send,
+        B: Backoff + Send,
+    {
+        let limits = Limits::new(&self, duration);
+        wait_loop(&mut self, limits, backoff, |_| None, None).await
+    }
+}
+
+#[async_trait]
+impl<W, T, E> Waiter<T, E> for Box<W>
+where
+    W: Waiter<T, E> + Send + ?Sized,
+{
+    fn name(&self) -> Cow<'static, str> {
+        (**self).name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        (**self).default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        (**self).default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        (**self).default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        (**self).default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        (**self).is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        (**self).default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        (**self).default_poll_timeout()
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        (**self).poll_timeout_error()
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        (**self).timer()
+    }
+
+    fn on_cancel(&mut self) {
+        (**self).on_cancel()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        (**self).on_wait_event(event, progress)
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        (**self).poll().await
+    }
+
+    fn timeout_error(&self) -> E {
+        (**self).timeout_error()
     }
 }
 
@@ -113,3 +610,76 @@ pub trait WaiterCurrentState<T> {
     /// Valid as of the last `poll` call.
     fn waiter_current_state(&self) -> &T;
 }
+
+/// Waiting with details about the current state on failure.
+///
+/// Implemented for all waiters that implement `WaiterCurrentState`. The
+/// `last_state` field of `WaitError` is populated with a copy of the current
+/// state as of the last `poll` call.
+#[async_trait]
+pub trait WaiterWithState<T, E, S>: Waiter<T, E> + WaiterCurrentState<S> {
+    /// Wait for the default amount of time, reporting the last state on
+    /// failure.
+    async fn wait_with_state(self) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized;
+
+    /// Wait for specified amount of time, reporting the last state on failure.
+    async fn wait_for_with_state(self, duration: Duration) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized;
+
+    /// Wait using the given backoff, reporting the last state on failure.
+    ///
+    /// If `duration` is `None`, wait forever.
+    async fn wait_with_backoff_and_state<B>(
+        self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized,
+        E: Send,
+        B: Backoff + Send;
+}
+
+#[async_trait]
+impl<W, T, E, S> WaiterWithState<T, E, S> for W
+where
+    W: Waiter<T, E> + WaiterCurrentState<S> + Send,
+    E: Send,
+    S: Clone,
+{
+    async fn wait_with_state(self) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized,
+    {
+        let duration = self.default_wait_timeout();
+        let delay = self.default_delay();
+        self.wait_with_backoff_and_state(duration, Constant::new(delay))
+            .await
+    }
+
+    async fn wait_for_with_state(self, duration: Duration) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized,
+    {
+        let delay = self.default_delay();
+        self.wait_with_backoff_and_state(Some(duration), Constant::new(delay))
+            .await
+    }
+
+    async fn wait_with_backoff_and_state<B>(
+        mut self,
+        duration: Option<Duration>,
+        backoff: B,
+    ) -> Result<T, WaitError<E, S>>
+    where
+        Self: Sized,
+        B: Backoff + Send,
+    {
+        let limits = Limits::new(&self, duration);
+        let current_state = |waiter: &Self| Some(waiter.waiter_current_state().clone());
+        wait_loop(&mut self, limits, backoff, current_state, None).await
+    }
+}
@@ -0,0 +1,41 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration with the `metrics` facade.
+//!
+//! The following metrics are recorded when waiting finishes, all labeled
+//! with `waiter` (see `Waiter::name`) and `outcome` (`success`, `timeout`,
+//! `attempts_exhausted`, `error` or `cancelled`):
+//!
+//! * `waiter_waits_total` - counter of finished waits;
+//! * `waiter_wait_duration_seconds` - histogram of the total waiting time;
+//! * `waiter_wait_attempts` - histogram of the number of `poll` calls.
+
+use metrics::{counter, histogram};
+
+use crate::observer::{Event, Progress};
+use crate::wait_loop::LoopWaiter;
+
+/// Record metrics for a terminal event of the waiting loop.
+///
+/// Other events are ignored.
+pub(crate) fn record<W, T, E, K>(waiter: &W, event: &Event<'_, T, E>, progress: &Progress)
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+{
+    let outcome = match event {
+        Event::Success(_) => "success",
+        Event::Timeout => "timeout",
+        Event::AttemptsExhausted => "attempts_exhausted",
+        Event::Error(_) => "error",
+        Event::Cancelled => "cancelled",
+        Event::Start | Event::PollResult(_) | Event::Sleep(_) => return,
+    };
+    let labels = [("waiter", waiter.name()), ("outcome", outcome.into())];
+    counter!("waiter_waits_total", &labels).increment(1);
+    histogram!("waiter_wait_duration_seconds", &labels).record(progress.elapsed.as_secs_f64());
+    histogram!("waiter_wait_attempts", &labels).record(f64::from(progress.attempts));
+}
@@ -0,0 +1,316 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiters using native `async fn` in traits.
+//!
+//! `Waiter::poll` returns a boxed future and `Waiter::timer` boxes its
+//! sleeps, which means allocations on every attempt. `NativeWaiter` uses
+//! unboxed futures for both and the same waiting loop. It is not
+//! dyn-compatible, use `NativeWaiter::boxed` to get a `Waiter` that can be
+//! used as `dyn Waiter`.
+
+use std::any::type_name;
+use std::borrow::Cow;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::backoff::Constant;
+use crate::observer::{Event, Progress};
+use crate::wait_loop::{wait_loop, wait_simple, Limits, LoopWaiter};
+use crate::{DynTimer, Timer, WaitError, WaitOptions, Waiter};
+
+/// Version of `Waiter` with an unboxed `poll` future.
+///
+/// All methods have the same meaning as in `Waiter`. The timer is an
+/// associated type, so that its sleeps are not boxed either. Set it to
+/// `timer::DefaultTimer` unless you need a specific one.
+pub trait NativeWaiter<T, E> {
+    /// Timer used by the waiting loops.
+    type Timer: Timer + 'static;
+
+    /// Name of this waiter used in metrics and tracing.
+    ///
+    /// Defaults to the name of the type.
+    fn name(&self) -> Cow<'static, str> {
+        Cow::Borrowed(type_name::<Self>())
+    }
+
+    /// Default timeout for this action.
+    ///
+    /// If `None, wait forever by default.
+    fn default_wait_timeout(&self) -> Option<Duration>;
+
+    /// Default delay between two retries.
+    fn default_delay(&self) -> Duration;
+
+    /// Default delay before the first attempt.
+    fn default_initial_delay(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Default maximum number of attempts.
+    fn default_max_attempts(&self) -> Option<u32> {
+        None
+    }
+
+    /// Whether the error returned by `poll` is transient.
+    fn is_transient(&self, _err: &E) -> bool {
+        false
+    }
+
+    /// Default maximum number of consecutive transient errors.
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        None
+    }
+
+    /// Default maximum duration of a single `poll` call.
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Error to use when a single `poll` call times out.
+    fn poll_timeout_error(&self) -> E {
+        self.timeout_error()
+    }
+
+    /// Timer used by the waiting loops.
+    fn timer(&self) -> &Self::Timer;
+
+    /// Called when waiting is cancelled via a `CancellationToken`.
+    fn on_cancel(&mut self) {}
+
+    /// Called by the waiting loops on every event.
+    fn on_wait_event(&mut self, _event: Event<'_, T, E>, _progress: &Progress) {}
+
+    /// Update the current state of the action.
+    ///
+    /// Returns `T` if the action is finished, `None` if it is not.
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>> + Send;
+
+    /// Error to return on timeout.
+    fn timeout_error(&self) -> E;
+
+    /// Turn this waiter into a dyn-compatible `Waiter`.
+    ///
+    /// The resulting waiter boxes the `poll` future again.
+    fn boxed(self) -> Boxed<Self>
+    where
+        Self: Sized,
+    {
+        Boxed { inner: self }
+    }
+
+    /// Wait for the default amount of time.
+    fn wait(self) -> impl Future<Output = Result<T, E>> + Send
+    where
+        Self: Sized + Send,
+        T: Send,
+    {
+        async move {
+            let mut waiter = Native(self);
+            let limits = Limits::new(&waiter, waiter.default_wait_timeout());
+            let backoff = Constant::new(waiter.default_delay());
+            wait_simple(&mut waiter, limits, backoff).await
+        }
+    }
+
+    /// Wait for specified amount of time.
+    fn wait_for(self, duration: Duration) -> impl Future<Output = Result<T, E>> + Send
+    where
+        Self: Sized + Send,
+        T: Send,
+    {
+        async move {
+            let mut waiter = Native(self);
+            let limits = Limits::new(&waiter, Some(duration));
+            let backoff = Constant::new(waiter.default_delay());
+            wait_simple(&mut waiter, limits, backoff).await
+        }
+    }
+
+    /// Wait for the default amount of time, reporting details on failure.
+    fn wait_detailed(self) -> impl Future<Output = Result<T, WaitError<E>>> + Send
+    where
+        Self: Sized + Send,
+        T: Send,
+        E: Send,
+    {
+        self.wait_with(WaitOptions::new())
+    }
+
+    /// Wait with the given options.
+    fn wait_with(self, options: WaitOptions) -> impl Future<Output = Result<T, WaitError<E>>> + Send
+    where
+        Self: Sized + Send,
+        T: Send,
+        E: Send,
+    {
+        async move {
+            let mut waiter = Native(self);
+            let options = options.resolve(&waiter);
+            wait_loop(
+                &mut waiter,
+                options.limits,
+                options.backoff,
+                |_| None,
+                options.cancellation_token.as_ref(),
+            )
+            .await
+        }
+    }
+}
+
+/// `NativeWaiter` driven by the waiting loop.
+struct Native<W>(W);
+
+/// Marker for the implementation of `LoopWaiter` for `Native`.
+enum ViaNative {}
+
+impl<W, T, E> LoopWaiter<T, E, ViaNative> for Native<W>
+where
+    W: NativeWaiter<T, E>,
+{
+    type Sleep = <W::Timer as Timer>::Sleep;
+
+    fn name(&self) -> Cow<'static, str> {
+        self.0.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.0.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.0.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.0.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.0.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.0.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.0.default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        self.0.default_poll_timeout()
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.0.poll_timeout_error()
+    }
+
+    fn now(&self) -> Instant {
+        Timer::now(self.0.timer())
+    }
+
+    fn sleep(&self, duration: Duration) -> <W::Timer as Timer>::Sleep {
+        Timer::sleep(self.0.timer(), duration)
+    }
+
+    fn on_cancel(&mut self) {
+        self.0.on_cancel()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        self.0.on_wait_event(event, progress)
+    }
+
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>> {
+        self.0.poll()
+    }
+
+    fn timeout_error(&self) -> E {
+        self.0.timeout_error()
+    }
+}
+
+/// `NativeWaiter` used as a dyn-compatible `Waiter`.
+///
+/// Created by `NativeWaiter::boxed`.
+#[derive(Debug, Clone)]
+pub struct Boxed<W> {
+    inner: W,
+}
+
+impl<W> Boxed<W> {
+    /// Get the wrapped waiter back.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<W, T, E> Waiter<T, E> for Boxed<W>
+where
+    W: NativeWaiter<T, E> + Send,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.inner.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.inner.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.inner.default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        self.inner.default_poll_timeout()
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.inner.poll_timeout_error()
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        self.inner.timer()
+    }
+
+    fn on_cancel(&mut self) {
+        self.inner.on_cancel()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        self.inner.on_wait_event(event, progress)
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        self.inner.poll().await
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
@@ -0,0 +1,296 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Observing the progress of waiting loops.
+
+use std::borrow::Cow;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::blocking::BlockingWaiter;
+use crate::{DynTimer, Waiter, WaiterCurrentState};
+
+/// Progress of a waiting loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Progress {
+    /// Number of `poll` calls made so far.
+    pub attempts: u32,
+    /// Time elapsed since waiting started.
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    pub(crate) fn new(attempts: u32, elapsed: Duration) -> Progress {
+        Progress { attempts, elapsed }
+    }
+}
+
+/// Event happening in a waiting loop.
+#[derive(Debug)]
+pub enum Event<'a, T, E> {
+    /// Waiting has started.
+    Start,
+    /// A `poll` call has returned.
+    PollResult(Result<Option<&'a T>, &'a E>),
+    /// The loop is about to sleep for the given delay.
+    Sleep(Duration),
+    /// Waiting has finished successfully.
+    Success(&'a T),
+    /// The timeout was reached.
+    Timeout,
+    /// The maximum number of attempts was reached.
+    AttemptsExhausted,
+    /// Waiting has failed with an error.
+    Error(&'a E),
+    /// Waiting was cancelled.
+    Cancelled,
+}
+
+impl<T, E> Clone for Event<'_, T, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, E> Copy for Event<'_, T, E> {}
+
+/// Observer of waiting loops.
+///
+/// Attach observers to a waiter with `with_observer` or
+/// `with_state_observer` of `Waiter` or `BlockingWaiter`. The type `S` is
+/// the current state of the waiter (see `WaiterCurrentState`), it is `None`
+/// for observers attached with `with_observer`.
+///
+/// All methods do nothing by default.
+pub trait Observer<T, E, S = ()> {
+    /// Waiting has started.
+    fn on_start(&mut self, _progress: &Progress, _state: Option<&S>) {}
+
+    /// A `poll` call has returned.
+    ///
+    /// Called for every `poll` call, including transient failures.
+    fn on_poll_result(
+        &mut self,
+        _result: Result<Option<&T>, &E>,
+        _progress: &Progress,
+        _state: Option<&S>,
+    ) {
+    }
+
+    /// The loop is about to sleep for `delay`.
+    fn on_sleep(&mut self, _delay: Duration, _progress: &Progress, _state: Option<&S>) {}
+
+    /// Waiting has finished successfully.
+    fn on_success(&mut self, _result: &T, _progress: &Progress) {}
+
+    /// The timeout was reached.
+    fn on_timeout(&mut self, _progress: &Progress, _state: Option<&S>) {}
+
+    /// The maximum number of attempts was reached.
+    ///
+    /// Calls `on_timeout` by default.
+    fn on_attempts_exhausted(&mut self, progress: &Progress, state: Option<&S>) {
+        self.on_timeout(progress, state)
+    }
+
+    /// Waiting has failed with an error.
+    fn on_error(&mut self, _err: &E, _progress: &Progress, _state: Option<&S>) {}
+
+    /// Waiting was cancelled.
+    fn on_cancel(&mut self, _progress: &Progress, _state: Option<&S>) {}
+
+    /// Dispatch an event to the corresponding method.
+    fn on_event(&mut self, event: Event<'_, T, E>, progress: &Progress, state: Option<&S>) {
+        match event {
+            Event::Start => self.on_start(progress, state),
+            Event::PollResult(result) => self.on_poll_result(result, progress, state),
+            Event::Sleep(delay) => self.on_sleep(delay, progress, state),
+            Event::Success(result) => self.on_success(result, progress),
+            Event::Timeout => self.on_timeout(progress, state),
+            Event::AttemptsExhausted => self.on_attempts_exhausted(progress, state),
+            Event::Error(err) => self.on_error(err, progress, state),
+            Event::Cancelled => self.on_cancel(progress, state),
+        }
+    }
+}
+
+impl<T, E, S, O> Observer<T, E, S> for &mut O
+where
+    O: Observer<T, E, S> + ?Sized,
+{
+    fn on_event(&mut self, event: Event<'_, T, E>, progress: &Progress, state: Option<&S>) {
+        (**self).on_event(event, progress, state)
+    }
+}
+
+impl<T, E, S, O> Observer<T, E, S> for Box<O>
+where
+    O: Observer<T, E, S> + ?Sized,
+{
+    fn on_event(&mut self, event: Event<'_, T, E>, progress: &Progress, state: Option<&S>) {
+        (**self).on_event(event, progress, state)
+    }
+}
+
+impl<T, E, S, O> Observer<T, E, S> for Vec<O>
+where
+    O: Observer<T, E, S>,
+{
+    fn on_event(&mut self, event: Event<'_, T, E>, progress: &Progress, state: Option<&S>) {
+        for observer in self {
+            observer.on_event(event, progress, state);
+        }
+    }
+}
+
+/// Waiter with an observer attached.
+///
+/// Created by `with_observer` and `with_state_observer` of `Waiter` and
+/// `BlockingWaiter`.
+pub struct Observed<W, O, S = ()> {
+    inner: W,
+    observer: O,
+    state: fn(&W) -> Option<&S>,
+}
+
+impl<W, O, S> Observed<W, O, S> {
+    pub(crate) fn new(inner: W, observer: O, state: fn(&W) -> Option<&S>) -> Observed<W, O, S> {
+        Observed {
+            inner,
+            observer,
+            state,
+        }
+    }
+
+    /// Get the wrapped waiter and the observer back.
+    pub fn into_inner(self) -> (W, O) {
+        (self.inner, self.observer)
+    }
+}
+
+#[async_trait]
+impl<W, O, S, T, E> Waiter<T, E> for Observed<W, O, S>
+where
+    W: Waiter<T, E> + Send,
+    O: Observer<T, E, S> + Send,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.inner.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.inner.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.inner.default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        self.inner.default_poll_timeout()
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.inner.poll_timeout_error()
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        self.inner.timer()
+    }
+
+    fn on_cancel(&mut self) {
+        self.inner.on_cancel()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        let state = (self.state)(&self.inner);
+        self.observer.on_event(event, progress, state);
+        self.inner.on_wait_event(event, progress)
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        self.inner.poll().await
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+impl<W, O, S, T, E> BlockingWaiter<T, E> for Observed<W, O, S>
+where
+    W: BlockingWaiter<T, E>,
+    O: Observer<T, E, S>,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.inner.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.inner.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.inner.default_max_transient_failures()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        let state = (self.state)(&self.inner);
+        self.observer.on_event(event, progress, state);
+        self.inner.on_wait_event(event, progress)
+    }
+
+    fn poll(&mut self) -> Result<Option<T>, E> {
+        self.inner.poll()
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+impl<W, O, S, X> WaiterCurrentState<X> for Observed<W, O, S>
+where
+    W: WaiterCurrentState<X>,
+{
+    fn waiter_current_state(&self) -> &X {
+        self.inner.waiter_current_state()
+    }
+}
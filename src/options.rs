@@ -0,0 +1,204 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Configuration of a single wait.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::backoff::{Constant, Jitter, Jittered};
+use crate::wait_loop::{Limits, LoopWaiter};
+use crate::{Backoff, CancellationToken};
+
+/// When to stop waiting.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum TimeLimit {
+    Timeout(Duration),
+    Deadline(Instant),
+    Forever,
+}
+
+impl TimeLimit {
+    /// The deadline if waiting starts at `now`, `None` for no limit.
+    pub(crate) fn deadline(self, now: Instant) -> Option<Instant> {
+        match self {
+            // A timeout too large to represent is the same as no timeout.
+            TimeLimit::Timeout(timeout) => now.checked_add(timeout),
+            TimeLimit::Deadline(deadline) => Some(deadline),
+            TimeLimit::Forever => None,
+        }
+    }
+}
+
+/// Options for `Waiter::wait_with`.
+///
+/// Every option that is not set falls back to the corresponding method of
+/// the `Waiter` (`default_wait_timeout`, `default_delay`,
+/// `default_max_attempts` and so on).
+#[derive(Default)]
+pub struct WaitOptions {
+    time_limit: Option<TimeLimit>,
+    backoff: Option<Box<dyn Backoff + Send>>,
+    jitter: Option<Jitter>,
+    jitter_seed: Option<u64>,
+    max_attempts: Option<u32>,
+    initial_delay: Option<Duration>,
+    poll_timeout: Option<Duration>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+impl WaitOptions {
+    /// Options with all values taken from the waiter.
+    pub fn new() -> WaitOptions {
+        WaitOptions::default()
+    }
+
+    /// Wait for specified amount of time.
+    ///
+    /// Overrides `with_deadline` and `forever`.
+    pub fn with_timeout(mut self, timeout: Duration) -> WaitOptions {
+        self.time_limit = Some(TimeLimit::Timeout(timeout));
+        self
+    }
+
+    /// Wait until the given deadline.
+    ///
+    /// Overrides `with_timeout` and `forever`.
+    pub fn with_deadline(mut self, deadline: Instant) -> WaitOptions {
+        self.time_limit = Some(TimeLimit::Deadline(deadline));
+        self
+    }
+
+    /// Wait without a time limit.
+    ///
+    /// Overrides `with_timeout` and `with_deadline`.
+    pub fn forever(mut self) -> WaitOptions {
+        self.time_limit = Some(TimeLimit::Forever);
+        self
+    }
+
+    /// Use the same delay between all attempts.
+    ///
+    /// Overrides `with_backoff`.
+    pub fn with_delay(self, delay: Duration) -> WaitOptions {
+        self.with_backoff(Constant::new(delay))
+    }
+
+    /// Use the given backoff for delays between attempts.
+    ///
+    /// Overrides `with_delay`.
+    pub fn with_backoff<B>(mut self, backoff: B) -> WaitOptions
+    where
+        B: Backoff + Send + 'static,
+    {
+        self.backoff = Some(Box::new(backoff));
+        self
+    }
+
+    /// Apply a random jitter to delays between attempts.
+    ///
+    /// The random generator is seeded randomly unless `with_jitter_seed` is
+    /// also used.
+    pub fn with_jitter(mut self, jitter: Jitter) -> WaitOptions {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// Seed the random generator of the jitter.
+    ///
+    /// The same seed always produces the same delays, which is useful in
+    /// tests. Has no effect without `with_jitter`.
+    pub fn with_jitter_seed(mut self, seed: u64) -> WaitOptions {
+        self.jitter_seed = Some(seed);
+        self
+    }
+
+    /// Make at most the given number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> WaitOptions {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sleep before the first attempt.
+    ///
+    /// Overrides `Waiter::default_initial_delay`. The delay counts against
+    /// the timeout.
+    pub fn with_initial_delay(mut self, initial_delay: Duration) -> WaitOptions {
+        self.initial_delay = Some(initial_delay);
+        self
+    }
+
+    /// Abort `poll` calls that take longer than `poll_timeout`.
+    pub fn with_poll_timeout(mut self, poll_timeout: Duration) -> WaitOptions {
+        self.poll_timeout = Some(poll_timeout);
+        self
+    }
+
+    /// Stop waiting when the token is cancelled.
+    pub fn with_cancellation_token(mut self, token: CancellationToken) -> WaitOptions {
+        self.cancellation_token = Some(token);
+        self
+    }
+
+    /// Resolve the options using the defaults of `waiter`.
+    pub(crate) fn resolve<W, T, E, K>(self, waiter: &W) -> Resolved
+    where
+        W: LoopWaiter<T, E, K> + ?Sized,
+    {
+        let mut limits = match self.time_limit {
+            Some(time_limit) => Limits::until(waiter, time_limit.deadline(waiter.now())),
+            None => Limits::new(waiter, waiter.default_wait_timeout()),
+        };
+        if let Some(max_attempts) = self.max_attempts {
+            limits.max_attempts = Some(max_attempts);
+        }
+        if let Some(initial_delay) = self.initial_delay {
+            limits.initial_delay = Some(initial_delay);
+        }
+        if let Some(poll_timeout) = self.poll_timeout {
+            limits.poll_timeout = Some(poll_timeout);
+        }
+
+        let mut backoff = self
+            .backoff
+            .unwrap_or_else(|| Box::new(Constant::new(waiter.default_delay())));
+        if let Some(jitter) = self.jitter {
+            let jittered = Jittered::new(backoff, jitter);
+            backoff = match self.jitter_seed {
+                Some(seed) => Box::new(jittered.with_seed(seed)),
+                None => Box::new(jittered),
+            };
+        }
+
+        Resolved {
+            limits,
+            backoff,
+            cancellation_token: self.cancellation_token,
+        }
+    }
+}
+
+impl fmt::Debug for WaitOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitOptions")
+            .field("time_limit", &self.time_limit)
+            .field("backoff", &self.backoff.as_ref().map(|_| ".."))
+            .field("jitter", &self.jitter)
+            .field("jitter_seed", &self.jitter_seed)
+            .field("max_attempts", &self.max_attempts)
+            .field("initial_delay", &self.initial_delay)
+            .field("poll_timeout", &self.poll_timeout)
+            .field("cancellation_token", &self.cancellation_token)
+            .finish()
+    }
+}
+
+/// `WaitOptions` with all defaults filled in.
+pub(crate) struct Resolved {
+    pub limits: Limits,
+    pub backoff: Box<dyn Backoff + Send>,
+    pub cancellation_token: Option<CancellationToken>,
+}
@@ -0,0 +1,122 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiters built from closures.
+
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{TimedOut, Waiter};
+
+/// Default delay between two attempts of `PollFn`.
+const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+/// Create a waiter from an asynchronous function.
+///
+/// The function is called on every attempt and should return the same
+/// result as `Waiter::poll`: `Some(T)` when finished, `None` otherwise.
+///
+/// By default the waiter waits forever with one second between attempts,
+/// use the builder methods of `PollFn` to change that. The error on timeout
+/// is created from `TimedOut` unless `with_timeout_error` is used.
+pub fn poll_fn<F, Fut, T, E>(poll: F) -> PollFn<F>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<T>, E>>,
+{
+    PollFn {
+        poll,
+        timeout: None,
+        delay: DEFAULT_DELAY,
+        timeout_error: DefaultTimeoutError,
+    }
+}
+
+/// Source of timeout errors for `PollFn`.
+///
+/// Implemented for all closures returning the error.
+pub trait TimeoutError<E> {
+    /// Create the error to return on timeout.
+    fn timeout_error(&self) -> E;
+}
+
+impl<E, G> TimeoutError<E> for G
+where
+    G: Fn() -> E,
+{
+    fn timeout_error(&self) -> E {
+        self()
+    }
+}
+
+/// Timeout errors created from `TimedOut`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultTimeoutError;
+
+impl<E: From<TimedOut>> TimeoutError<E> for DefaultTimeoutError {
+    fn timeout_error(&self) -> E {
+        TimedOut.into()
+    }
+}
+
+/// Waiter created by `poll_fn`.
+#[derive(Debug, Clone)]
+pub struct PollFn<F, G = DefaultTimeoutError> {
+    poll: F,
+    timeout: Option<Duration>,
+    delay: Duration,
+    timeout_error: G,
+}
+
+impl<F, G> PollFn<F, G> {
+    /// Use the given default timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> PollFn<F, G> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use the given default delay between attempts.
+    pub fn with_delay(mut self, delay: Duration) -> PollFn<F, G> {
+        self.delay = delay;
+        self
+    }
+
+    /// Use the given function to create the error on timeout.
+    pub fn with_timeout_error<G2>(self, timeout_error: G2) -> PollFn<F, G2> {
+        PollFn {
+            poll: self.poll,
+            timeout: self.timeout,
+            delay: self.delay,
+            timeout_error,
+        }
+    }
+}
+
+#[async_trait]
+impl<F, Fut, G, T, E> Waiter<T, E> for PollFn<F, G>
+where
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = Result<Option<T>, E>> + Send,
+    G: TimeoutError<E> + Send,
+{
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.delay
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        (self.poll)().await
+    }
+
+    fn timeout_error(&self) -> E {
+        self.timeout_error.timeout_error()
+    }
+}
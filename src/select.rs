@@ -0,0 +1,128 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for the first of several waiters.
+
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::join::drive;
+use crate::options::TimeLimit;
+use crate::{WaitError, Waiter};
+
+/// Wait for the first of `waiters` to succeed.
+///
+/// Each waiter is polled with its own default delay. Without
+/// `SelectOk::with_timeout` or `SelectOk::with_deadline`, each waiter also
+/// uses its own default timeout.
+///
+/// Waiting for no waiters at all fails right away with an `AllFailed`
+/// carrying no errors.
+pub fn select_ok<I>(waiters: I) -> SelectOk<I::Item>
+where
+    I: IntoIterator,
+{
+    SelectOk {
+        waiters: waiters.into_iter().collect(),
+        time_limit: None,
+    }
+}
+
+/// Several waiters racing to succeed first.
+///
+/// Created by `select_ok`.
+#[derive(Debug, Clone)]
+pub struct SelectOk<W> {
+    waiters: Vec<W>,
+    time_limit: Option<TimeLimit>,
+}
+
+impl<W> SelectOk<W> {
+    /// Wait for the specified amount of time in total.
+    ///
+    /// Overrides `with_deadline`.
+    pub fn with_timeout(mut self, timeout: Duration) -> SelectOk<W> {
+        self.time_limit = Some(TimeLimit::Timeout(timeout));
+        self
+    }
+
+    /// Wait until the given deadline.
+    ///
+    /// Overrides `with_timeout`.
+    pub fn with_deadline(mut self, deadline: Instant) -> SelectOk<W> {
+        self.time_limit = Some(TimeLimit::Deadline(deadline));
+        self
+    }
+
+    /// Wait for the first waiter to succeed.
+    ///
+    /// Returns its result and its index. The other waiters are cancelled.
+    /// Fails with the errors of all waiters if none of them succeeds.
+    pub async fn wait<T, E>(self) -> Result<(T, usize), AllFailed<E>>
+    where
+        W: Waiter<T, E>,
+    {
+        let mut waiters = self.waiters;
+        let (results, winner) = drive(&mut waiters, self.time_limit, Result::is_ok).await;
+        match winner {
+            Some(index) => {
+                let result = results
+                    .into_iter()
+                    .nth(index)
+                    .and_then(Result::ok)
+                    .expect("the winning waiter must have succeeded");
+                Ok((result, index))
+            }
+            None => Err(AllFailed {
+                errors: results.into_iter().filter_map(Result::err).collect(),
+            }),
+        }
+    }
+}
+
+/// Error of `SelectOk::wait` when none of the waiters succeeded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllFailed<E> {
+    errors: Vec<WaitError<E>>,
+}
+
+impl<E> AllFailed<E> {
+    /// Errors of all waiters in their original order.
+    pub fn errors(&self) -> &[WaitError<E>] {
+        &self.errors
+    }
+
+    /// Get the errors of all waiters in their original order.
+    pub fn into_errors(self) -> Vec<WaitError<E>> {
+        self.errors
+    }
+
+    /// Whether all waiters reached the timeout.
+    ///
+    /// False if there were no waiters.
+    pub fn is_timeout(&self) -> bool {
+        !self.errors.is_empty() && self.errors.iter().all(WaitError::is_timeout)
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for AllFailed<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "all {} waiter(s) failed", self.errors.len())?;
+        for (index, err) in self.errors.iter().enumerate() {
+            write!(
+                f,
+                "{} #{}: {}",
+                if index == 0 { ":" } else { ";" },
+                index,
+                err
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<E> Error for AllFailed<E> where E: Error + 'static {}
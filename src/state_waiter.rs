@@ -0,0 +1,163 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiters driven by predicates over a fetched state.
+
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use crate::{Waiter, WaiterCurrentState};
+
+/// Default delay between two attempts of `StateWaiter`.
+const DEFAULT_DELAY: Duration = Duration::from_secs(1);
+
+/// Error of `StateWaiter`.
+///
+/// The type `S` is the fetched state, `E` is the error of the fetch function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StateWaitError<S, E> {
+    /// Fetching the state failed.
+    Fetch(E),
+    /// The state matched the failure predicate.
+    Failed(S),
+    /// The timeout was reached.
+    TimedOut {
+        /// The last fetched state, if any.
+        last_state: Option<S>,
+    },
+}
+
+impl<S: fmt::Debug, E: fmt::Display> fmt::Display for StateWaitError<S, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateWaitError::Fetch(err) => write!(f, "failed to fetch the state: {}", err),
+            StateWaitError::Failed(state) => write!(f, "reached a failure state {:?}", state),
+            StateWaitError::TimedOut {
+                last_state: Some(state),
+            } => write!(f, "timed out waiting, the last state is {:?}", state),
+            StateWaitError::TimedOut { last_state: None } => {
+                f.write_str("timed out waiting, the state was never fetched")
+            }
+        }
+    }
+}
+
+impl<S, E> Error for StateWaitError<S, E>
+where
+    S: fmt::Debug,
+    E: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StateWaitError::Fetch(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Waiter that fetches a state until it matches a predicate.
+///
+/// On every attempt the state is fetched and checked: waiting finishes with
+/// the state if it matches the success predicate and fails with
+/// `StateWaitError::Failed` if it matches the failure predicate.
+///
+/// The current state is available via `WaiterCurrentState<Option<S>>`, it is
+/// `None` until the first successful fetch.
+#[derive(Debug, Clone)]
+pub struct StateWaiter<F, P, Q, S> {
+    fetch: F,
+    success: P,
+    failure: Q,
+    state: Option<S>,
+    timeout: Option<Duration>,
+    delay: Duration,
+}
+
+impl<F, P, Q, S> StateWaiter<F, P, Q, S> {
+    /// Create a waiter from a fetch function and two predicates.
+    ///
+    /// By default the waiter waits forever with one second between attempts.
+    pub fn new<Fut, E>(fetch: F, success: P, failure: Q) -> StateWaiter<F, P, Q, S>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<S, E>>,
+        P: Fn(&S) -> bool,
+        Q: Fn(&S) -> bool,
+    {
+        StateWaiter {
+            fetch,
+            success,
+            failure,
+            state: None,
+            timeout: None,
+            delay: DEFAULT_DELAY,
+        }
+    }
+
+    /// Use the given default timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> StateWaiter<F, P, Q, S> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Use the given default delay between attempts.
+    pub fn with_delay(mut self, delay: Duration) -> StateWaiter<F, P, Q, S> {
+        self.delay = delay;
+        self
+    }
+
+    /// The last fetched state, if any.
+    pub fn state(&self) -> Option<&S> {
+        self.state.as_ref()
+    }
+}
+
+#[async_trait]
+impl<F, Fut, P, Q, S, E> Waiter<S, StateWaitError<S, E>> for StateWaiter<F, P, Q, S>
+where
+    F: FnMut() -> Fut + Send,
+    Fut: Future<Output = Result<S, E>> + Send,
+    P: Fn(&S) -> bool + Send,
+    Q: Fn(&S) -> bool + Send,
+    S: Clone + Send,
+{
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.delay
+    }
+
+    async fn poll(&mut self) -> Result<Option<S>, StateWaitError<S, E>> {
+        let state = (self.fetch)().await.map_err(StateWaitError::Fetch)?;
+        let result = if (self.success)(&state) {
+            Ok(Some(state.clone()))
+        } else if (self.failure)(&state) {
+            Err(StateWaitError::Failed(state.clone()))
+        } else {
+            Ok(None)
+        };
+        self.state = Some(state);
+        result
+    }
+
+    fn timeout_error(&self) -> StateWaitError<S, E> {
+        StateWaitError::TimedOut {
+            last_state: self.state.clone(),
+        }
+    }
+}
+
+impl<F, P, Q, S> WaiterCurrentState<Option<S>> for StateWaiter<F, P, Q, S> {
+    fn waiter_current_state(&self) -> &Option<S> {
+        &self.state
+    }
+}
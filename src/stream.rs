@@ -0,0 +1,225 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting as a stream of events.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures_core::Stream;
+
+use crate::backoff::Constant;
+use crate::observer::{Event, Progress};
+use crate::timer::BoxedSleep;
+use crate::wait_loop::{wait_loop, Limits, LoopWaiter};
+use crate::{WaitError, Waiter, WaiterCurrentState};
+
+/// Item of a `WaitStream`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitEvent<T, E, S> {
+    /// The waiter is not ready yet, carries its current state.
+    Pending(S),
+    /// Waiting has finished successfully.
+    Done(T),
+    /// A `poll` call has failed with an error.
+    Err(E),
+    /// The timeout or the maximum number of attempts was reached.
+    TimedOut,
+}
+
+type Events<T, E, S> = Arc<Mutex<VecDeque<WaitEvent<T, E, S>>>>;
+
+type Wait<T, E> = Pin<Box<dyn Future<Output = Result<T, WaitError<E>>> + Send>>;
+
+/// Stream of events of a waiting loop.
+///
+/// Created by `Waiter::into_stream`. Runs the same loop as `wait_detailed`
+/// and yields `WaitEvent::Pending` after every unsuccessful `poll` call and
+/// `WaitEvent::Err` after every failed one, including transient failures.
+/// Ends after `Done`, `TimedOut` or an `Err` that stops waiting.
+#[must_use = "streams do nothing unless polled"]
+pub struct WaitStream<T, E, S> {
+    wait: Option<Wait<T, E>>,
+    events: Events<T, E, S>,
+}
+
+impl<T, E, S> WaitStream<T, E, S>
+where
+    T: Send + 'static,
+    E: Clone + Send + 'static,
+    S: Clone + Send + 'static,
+{
+    pub(crate) fn new<W>(waiter: W) -> WaitStream<T, E, S>
+    where
+        W: Waiter<T, E> + WaiterCurrentState<S> + Send + 'static,
+    {
+        let events = Events::default();
+        let mut waiter = Streamed {
+            inner: waiter,
+            events: Arc::clone(&events),
+            _result: PhantomData,
+        };
+        let wait = Box::pin(async move {
+            let limits = Limits::new(&waiter, Waiter::default_wait_timeout(&waiter.inner));
+            let backoff = Constant::new(Waiter::default_delay(&waiter.inner));
+            wait_loop(&mut waiter, limits, backoff, |_| None, None).await
+        });
+        WaitStream {
+            wait: Some(wait),
+            events,
+        }
+    }
+}
+
+impl<T, E, S> WaitStream<T, E, S> {
+    fn events(&self) -> MutexGuard<'_, VecDeque<WaitEvent<T, E, S>>> {
+        lock(&self.events)
+    }
+}
+
+fn lock<T, E, S>(events: &Events<T, E, S>) -> MutexGuard<'_, VecDeque<WaitEvent<T, E, S>>> {
+    events.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T, E, S> Stream for WaitStream<T, E, S> {
+    type Item = WaitEvent<T, E, S>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(event) = this.events().pop_front() {
+            return Poll::Ready(Some(event));
+        }
+
+        let Some(wait) = this.wait.as_mut() else {
+            return Poll::Ready(None);
+        };
+        // The loop queues events while running, the final result is queued
+        // after them.
+        if let Poll::Ready(result) = wait.as_mut().poll(cx) {
+            this.wait = None;
+            let event = match result {
+                Ok(result) => Some(WaitEvent::Done(result)),
+                // Already queued by the last `poll` call.
+                Err(WaitError::Poll(_)) => None,
+                Err(WaitError::Timeout { .. }) | Err(WaitError::AttemptsExhausted { .. }) => {
+                    Some(WaitEvent::TimedOut)
+                }
+                // There is no cancellation token.
+                Err(WaitError::Cancelled) => None,
+            };
+            this.events().extend(event);
+        }
+
+        match this.events().pop_front() {
+            Some(event) => Poll::Ready(Some(event)),
+            None if this.wait.is_none() => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T, E, S> fmt::Debug for WaitStream<T, E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitStream")
+            .field("queued", &self.events().len())
+            .field("finished", &self.wait.is_none())
+            .finish()
+    }
+}
+
+/// Waiter queueing stream events.
+struct Streamed<W, T, E, S> {
+    inner: W,
+    events: Events<T, E, S>,
+    _result: PhantomData<fn() -> (T, E)>,
+}
+
+/// Marker for the implementation of `LoopWaiter` for `Streamed`.
+enum ViaStream {}
+
+impl<W, T, E, S> LoopWaiter<T, E, ViaStream> for Streamed<W, T, E, S>
+where
+    W: Waiter<T, E> + WaiterCurrentState<S>,
+    E: Clone,
+    S: Clone,
+{
+    type Sleep = BoxedSleep;
+
+    fn name(&self) -> Cow<'static, str> {
+        Waiter::name(&self.inner)
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        Waiter::default_wait_timeout(&self.inner)
+    }
+
+    fn default_delay(&self) -> Duration {
+        Waiter::default_delay(&self.inner)
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        Waiter::default_initial_delay(&self.inner)
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        Waiter::default_max_attempts(&self.inner)
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        Waiter::is_transient(&self.inner, err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        Waiter::default_max_transient_failures(&self.inner)
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        Waiter::default_poll_timeout(&self.inner)
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        Waiter::poll_timeout_error(&self.inner)
+    }
+
+    fn now(&self) -> Instant {
+        Waiter::timer(&self.inner).now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedSleep {
+        Waiter::timer(&self.inner).sleep(duration)
+    }
+
+    fn on_cancel(&mut self) {
+        Waiter::on_cancel(&mut self.inner)
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        let queued = match event {
+            Event::PollResult(Ok(None)) => Some(WaitEvent::Pending(
+                self.inner.waiter_current_state().clone(),
+            )),
+            Event::PollResult(Err(err)) => Some(WaitEvent::Err(err.clone())),
+            _ => None,
+        };
+        lock(&self.events).extend(queued);
+        Waiter::on_wait_event(&mut self.inner, event, progress)
+    }
+
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>> {
+        Waiter::poll(&mut self.inner)
+    }
+
+    fn timeout_error(&self) -> E {
+        Waiter::timeout_error(&self.inner)
+    }
+}
@@ -0,0 +1,449 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers for deterministic tests of waiters.
+//!
+//! `VirtualClock` is a `Timer` that never sleeps for real, attach it to any
+//! waiter with `Clocked`. `ScriptedWaiter` returns a predefined sequence of
+//! results. Both record what happened for later assertions.
+//!
+//! Alternatively, `timer::TokioTimer` (the default with the `tokio` feature)
+//! follows the tokio clock, so tests can use tokio's paused time instead.
+
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::observer::{Event, Progress};
+use crate::{DynTimer, TimedOut, Timer, Waiter, WaiterCurrentState};
+
+/// Virtual clock for the waiting loops.
+///
+/// By default the clock advances automatically: every sleep finishes right
+/// away, moving the clock forward by its duration. A clock created with
+/// `manual` only moves on `advance`.
+///
+/// Clones of the clock share the same time.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    inner: Arc<Mutex<ClockState>>,
+}
+
+#[derive(Debug)]
+struct ClockState {
+    start: Instant,
+    now: Instant,
+    auto_advance: bool,
+    sleeps: Vec<Duration>,
+    wakers: Vec<Waker>,
+}
+
+impl VirtualClock {
+    /// Create a clock that advances automatically on every sleep.
+    pub fn new() -> VirtualClock {
+        VirtualClock::with_auto_advance(true)
+    }
+
+    /// Create a clock that only advances on `advance`.
+    pub fn manual() -> VirtualClock {
+        VirtualClock::with_auto_advance(false)
+    }
+
+    fn with_auto_advance(auto_advance: bool) -> VirtualClock {
+        let now = Instant::now();
+        VirtualClock {
+            inner: Arc::new(Mutex::new(ClockState {
+                start: now,
+                now,
+                auto_advance,
+                sleeps: Vec::new(),
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, ClockState> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Move the clock forward, finishing the sleeps that are due.
+    pub fn advance(&self, duration: Duration) {
+        let wakers = {
+            let mut state = self.state();
+            state.now += duration;
+            std::mem::take(&mut state.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Time passed since the clock was created.
+    pub fn elapsed(&self) -> Duration {
+        let state = self.state();
+        state.now - state.start
+    }
+
+    /// Durations of all sleeps requested so far.
+    pub fn sleeps(&self) -> Vec<Duration> {
+        self.state().sleeps.clone()
+    }
+
+    /// Assert that exactly the given sleeps were requested.
+    #[track_caller]
+    pub fn assert_sleeps(&self, expected: &[Duration]) {
+        let sleeps = self.sleeps();
+        assert_eq!(sleeps, expected, "unexpected sleeps of the virtual clock");
+    }
+}
+
+impl Default for VirtualClock {
+    fn default() -> VirtualClock {
+        VirtualClock::new()
+    }
+}
+
+impl Timer for VirtualClock {
+    type Sleep = VirtualSleep;
+
+    fn now(&self) -> Instant {
+        self.state().now
+    }
+
+    fn sleep(&self, duration: Duration) -> VirtualSleep {
+        let until = {
+            let mut state = self.state();
+            state.sleeps.push(duration);
+            state.now + duration
+        };
+        VirtualSleep {
+            clock: self.clone(),
+            until,
+        }
+    }
+}
+
+/// Future returned by `VirtualClock::sleep`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct VirtualSleep {
+    clock: VirtualClock,
+    until: Instant,
+}
+
+impl Future for VirtualSleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.state();
+        if state.now >= self.until {
+            Poll::Ready(())
+        } else if state.auto_advance {
+            state.now = self.until;
+            Poll::Ready(())
+        } else {
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+}
+
+/// Waiter using a custom timer.
+///
+/// All other methods are delegated to the wrapped waiter. Useful to run
+/// waiters under test with a `VirtualClock`.
+#[derive(Debug, Clone)]
+pub struct Clocked<W, C = VirtualClock> {
+    inner: W,
+    timer: C,
+}
+
+impl<W, C> Clocked<W, C> {
+    /// Make `inner` use `timer`.
+    pub fn new(inner: W, timer: C) -> Clocked<W, C> {
+        Clocked { inner, timer }
+    }
+
+    /// Get the wrapped waiter and the timer back.
+    pub fn into_inner(self) -> (W, C) {
+        (self.inner, self.timer)
+    }
+}
+
+#[async_trait]
+impl<W, C, T, E> Waiter<T, E> for Clocked<W, C>
+where
+    W: Waiter<T, E> + Send,
+    C: Timer,
+{
+    fn name(&self) -> Cow<'static, str> {
+        self.inner.name()
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        self.inner.default_initial_delay()
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        self.inner.default_max_attempts()
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.inner.default_max_transient_failures()
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        self.inner.default_poll_timeout()
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        self.inner.poll_timeout_error()
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        &self.timer
+    }
+
+    fn on_cancel(&mut self) {
+        self.inner.on_cancel()
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        self.inner.on_wait_event(event, progress)
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        self.inner.poll().await
+    }
+
+    fn timeout_error(&self) -> E {
+        self.inner.timeout_error()
+    }
+}
+
+impl<W, C, X> WaiterCurrentState<X> for Clocked<W, C>
+where
+    W: WaiterCurrentState<X>,
+{
+    fn waiter_current_state(&self) -> &X {
+        self.inner.waiter_current_state()
+    }
+}
+
+/// Result of one `poll` call of a `ScriptedWaiter`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step<T, E> {
+    /// The action is not finished yet.
+    Pending,
+    /// The action has finished with the result.
+    Done(T),
+    /// The `poll` call fails with the error.
+    Err(E),
+}
+
+/// Waiter returning a predefined sequence of results.
+///
+/// Once the script is exhausted, `poll` keeps returning `Ok(None)`. Clones
+/// share the script and the statistics, so a clone can be kept for
+/// assertions while the original is consumed by waiting.
+///
+/// The timeout error is created from `TimedOut`. By default the waiter has
+/// no timeout, a delay of one second and uses the `DefaultTimer`. Without
+/// any timer feature, waiters created without `with_clock` share one
+/// automatic `VirtualClock` instead.
+pub struct ScriptedWaiter<T, E> {
+    script: Arc<Mutex<Script<T, E>>>,
+    timeout: Option<Duration>,
+    delay: Duration,
+    transient: Option<fn(&E) -> bool>,
+    clock: Option<VirtualClock>,
+}
+
+struct Script<T, E> {
+    steps: VecDeque<Step<T, E>>,
+    polls: u32,
+    cancelled: bool,
+}
+
+impl<T, E> ScriptedWaiter<T, E> {
+    /// Create a waiter returning `steps` one by one.
+    pub fn new<I>(steps: I) -> ScriptedWaiter<T, E>
+    where
+        I: IntoIterator<Item = Step<T, E>>,
+    {
+        ScriptedWaiter {
+            script: Arc::new(Mutex::new(Script {
+                steps: steps.into_iter().collect(),
+                polls: 0,
+                cancelled: false,
+            })),
+            timeout: None,
+            delay: Duration::from_secs(1),
+            transient: None,
+            clock: None,
+        }
+    }
+
+    /// Set the default timeout.
+    pub fn with_timeout(mut self, timeout: Duration) -> ScriptedWaiter<T, E> {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the default delay between attempts.
+    pub fn with_delay(mut self, delay: Duration) -> ScriptedWaiter<T, E> {
+        self.delay = delay;
+        self
+    }
+
+    /// Treat errors matching `transient` as transient.
+    pub fn with_transient(mut self, transient: fn(&E) -> bool) -> ScriptedWaiter<T, E> {
+        self.transient = Some(transient);
+        self
+    }
+
+    /// Use the virtual clock as the timer.
+    pub fn with_clock(mut self, clock: VirtualClock) -> ScriptedWaiter<T, E> {
+        self.clock = Some(clock);
+        self
+    }
+
+    fn script(&self) -> MutexGuard<'_, Script<T, E>> {
+        self.script.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Number of `poll` calls made so far.
+    pub fn polls(&self) -> u32 {
+        self.script().polls
+    }
+
+    /// Number of steps that have not been returned yet.
+    pub fn remaining(&self) -> usize {
+        self.script().steps.len()
+    }
+
+    /// Whether `on_cancel` has been called.
+    pub fn is_cancelled(&self) -> bool {
+        self.script().cancelled
+    }
+
+    /// Assert that exactly `expected` `poll` calls were made.
+    #[track_caller]
+    pub fn assert_polls(&self, expected: u32) {
+        let polls = self.polls();
+        assert_eq!(polls, expected, "unexpected number of polls");
+    }
+}
+
+impl<T, E> Clone for ScriptedWaiter<T, E> {
+    fn clone(&self) -> Self {
+        ScriptedWaiter {
+            script: Arc::clone(&self.script),
+            timeout: self.timeout,
+            delay: self.delay,
+            transient: self.transient,
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl<T, E> fmt::Debug for ScriptedWaiter<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let script = self.script();
+        f.debug_struct("ScriptedWaiter")
+            .field("polls", &script.polls)
+            .field("remaining", &script.steps.len())
+            .field("cancelled", &script.cancelled)
+            .field("timeout", &self.timeout)
+            .field("delay", &self.delay)
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl<T, E> Waiter<T, E> for ScriptedWaiter<T, E>
+where
+    T: Send,
+    E: From<TimedOut> + Send,
+{
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.delay
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        self.transient.is_some_and(|transient| transient(err))
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        match &self.clock {
+            Some(clock) => clock,
+            None => default_timer(),
+        }
+    }
+
+    fn on_cancel(&mut self) {
+        self.script().cancelled = true;
+    }
+
+    async fn poll(&mut self) -> Result<Option<T>, E> {
+        let mut script = self.script();
+        script.polls += 1;
+        match script.steps.pop_front() {
+            Some(Step::Done(result)) => Ok(Some(result)),
+            Some(Step::Err(err)) => Err(err),
+            Some(Step::Pending) | None => Ok(None),
+        }
+    }
+
+    fn timeout_error(&self) -> E {
+        TimedOut.into()
+    }
+}
+
+with_default_timer! {
+    /// Timer of scripted waiters created without `with_clock`.
+    fn default_timer() -> &'static dyn DynTimer {
+        &crate::timer::DefaultTimer
+    }
+}
+
+/// Timer of scripted waiters created without `with_clock`.
+#[cfg(not(any(
+    feature = "tokio",
+    feature = "async-std",
+    feature = "smol",
+    feature = "futures-timer"
+)))]
+fn default_timer() -> &'static dyn DynTimer {
+    static CLOCK: std::sync::OnceLock<VirtualClock> = std::sync::OnceLock::new();
+    CLOCK.get_or_init(VirtualClock::new)
+}
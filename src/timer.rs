@@ -0,0 +1,202 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Timers used by the waiting loops.
+//!
+//! Implementations are available for several async runtimes behind the
+//! features of the same names. `DefaultTimer` is the first enabled one of
+//! `TokioTimer`, `AsyncStdTimer`, `SmolTimer` and `FuturesTimer`.
+//!
+//! Without any of these features there is no `DefaultTimer`: async waiters
+//! have to implement `Waiter::timer`, and `PollFn` and `StateWaiter` are not
+//! available. `BlockingWaiter` does not need a timer.
+
+use std::future::{poll_fn, Future};
+use std::pin::{pin, Pin};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tokio")]
+pub use TokioTimer as DefaultTimer;
+
+#[cfg(all(not(feature = "tokio"), feature = "async-std"))]
+pub use AsyncStdTimer as DefaultTimer;
+
+#[cfg(all(not(feature = "tokio"), not(feature = "async-std"), feature = "smol"))]
+pub use SmolTimer as DefaultTimer;
+
+#[cfg(all(
+    not(feature = "tokio"),
+    not(feature = "async-std"),
+    not(feature = "smol"),
+    feature = "futures-timer"
+))]
+pub use FuturesTimer as DefaultTimer;
+
+/// Boxed future returned by `DynTimer::sleep`.
+pub type BoxedSleep = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// Source of time for the waiting loops.
+pub trait Timer: Send + Sync {
+    /// Future returned by `sleep`.
+    type Sleep: Future<Output = ()> + Send + 'static;
+
+    /// Current time.
+    fn now(&self) -> Instant;
+
+    /// Future that resolves after `duration`.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+impl<T> Timer for &T
+where
+    T: Timer + ?Sized,
+{
+    type Sleep = T::Sleep;
+
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) -> T::Sleep {
+        (**self).sleep(duration)
+    }
+}
+
+/// Dyn-compatible version of `Timer`, returned by `Waiter::timer`.
+///
+/// Implemented for all timers by boxing their sleeps.
+pub trait DynTimer: Send + Sync {
+    /// Current time.
+    fn now(&self) -> Instant;
+
+    /// Future that resolves after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxedSleep;
+}
+
+impl<T: Timer> DynTimer for T {
+    fn now(&self) -> Instant {
+        Timer::now(self)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedSleep {
+        Box::pin(Timer::sleep(self, duration))
+    }
+}
+
+/// Timer using the tokio runtime.
+///
+/// Follows the tokio clock, including when it is paused in tests.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    type Sleep = tokio::time::Sleep;
+
+    fn now(&self) -> Instant {
+        tokio::time::Instant::now().into_std()
+    }
+
+    fn sleep(&self, duration: Duration) -> tokio::time::Sleep {
+        tokio::time::sleep(duration)
+    }
+}
+
+/// Timer using the async-std runtime.
+///
+/// The sleep future of async-std cannot be named, so it is boxed.
+#[cfg(feature = "async-std")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsyncStdTimer;
+
+#[cfg(feature = "async-std")]
+impl Timer for AsyncStdTimer {
+    type Sleep = BoxedSleep;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedSleep {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// Timer using the smol runtime.
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolTimer;
+
+#[cfg(feature = "smol")]
+impl Timer for SmolTimer {
+    type Sleep = SmolSleep;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> SmolSleep {
+        SmolSleep {
+            timer: smol::Timer::after(duration),
+        }
+    }
+}
+
+/// Future returned by `SmolTimer::sleep`.
+#[cfg(feature = "smol")]
+#[derive(Debug)]
+#[must_use = "futures do nothing unless awaited"]
+pub struct SmolSleep {
+    timer: smol::Timer,
+}
+
+#[cfg(feature = "smol")]
+impl Future for SmolSleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.timer).poll(cx).map(|_| ())
+    }
+}
+
+/// Runtime-independent timer using the futures-timer crate.
+#[cfg(feature = "futures-timer")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FuturesTimer;
+
+#[cfg(feature = "futures-timer")]
+impl Timer for FuturesTimer {
+    type Sleep = futures_timer::Delay;
+
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> futures_timer::Delay {
+        futures_timer::Delay::new(duration)
+    }
+}
+
+/// Run `future` unless `sleep` finishes first.
+///
+/// Returns `None` on timeout.
+pub(crate) async fn timeout<S, F>(sleep: S, future: F) -> Option<F::Output>
+where
+    S: Future<Output = ()>,
+    F: Future,
+{
+    let mut future = pin!(future);
+    let mut sleep = pin!(sleep);
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        sleep.as_mut().poll(cx).map(|()| None)
+    })
+    .await
+}
@@ -0,0 +1,61 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration with `tracing`.
+
+use tracing::field::Empty;
+use tracing::{debug, debug_span, warn, Span};
+
+use crate::observer::{Event, Progress};
+use crate::wait_loop::{Limits, LoopWaiter};
+
+/// Span covering one waiting loop.
+///
+/// The `delay` field is recorded before the first sleep, the following
+/// delays are reported in the sleep events.
+pub(crate) fn wait_span<W, T, E, K>(waiter: &W, limits: &Limits) -> Span
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+{
+    let timeout = limits
+        .deadline
+        .map(|deadline| deadline.saturating_duration_since(waiter.now()));
+    debug_span!(
+        "wait",
+        name = %waiter.name(),
+        timeout = ?timeout,
+        max_attempts = ?limits.max_attempts,
+        delay = Empty,
+    )
+}
+
+/// Emit a tracing event for an event of the waiting loop.
+pub(crate) fn wait_event<T, E>(event: &Event<'_, T, E>, progress: &Progress) {
+    let attempts = progress.attempts;
+    let elapsed = progress.elapsed;
+    match event {
+        Event::Start => debug!(attempts, ?elapsed, "waiting started"),
+        Event::PollResult(Ok(None)) => debug!(attempts, ?elapsed, "not finished yet"),
+        Event::PollResult(Ok(Some(_))) => debug!(attempts, ?elapsed, "finished"),
+        Event::PollResult(Err(_)) => debug!(attempts, ?elapsed, "poll returned an error"),
+        Event::Sleep(delay) => {
+            if attempts == 1 {
+                Span::current().record("delay", tracing::field::debug(delay));
+            }
+            debug!(
+                attempts,
+                ?elapsed,
+                ?delay,
+                "sleeping before the next attempt"
+            );
+        }
+        Event::Success(_) => debug!(attempts, ?elapsed, "waiting succeeded"),
+        Event::Timeout => warn!(attempts, ?elapsed, "waiting timed out"),
+        Event::AttemptsExhausted => warn!(attempts, ?elapsed, "maximum attempts reached"),
+        Event::Error(_) => warn!(attempts, ?elapsed, "waiting failed"),
+        Event::Cancelled => debug!(attempts, ?elapsed, "waiting cancelled"),
+    }
+}
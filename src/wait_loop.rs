@@ -0,0 +1,444 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The waiting loop shared by all `wait_*` methods.
+
+use std::borrow::Cow;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use crate::cancel::or_cancelled;
+use crate::observer::{Event, Progress};
+use crate::timer::{timeout, BoxedSleep};
+use crate::{Backoff, CancellationToken, WaitError, Waiter};
+
+/// Waiter as seen by the waiting loop.
+///
+/// Implemented for all `Waiter`s and for native waiters, so that the same
+/// loop serves both without boxing the futures of the latter. The type `K`
+/// tells the two implementations apart.
+pub(crate) trait LoopWaiter<T, E, K> {
+    /// Future returned by `sleep`.
+    type Sleep: Future<Output = ()>;
+
+    // Only used by tracing and metrics.
+    #[cfg_attr(not(any(feature = "tracing", feature = "metrics")), allow(dead_code))]
+    fn name(&self) -> Cow<'static, str>;
+    fn default_wait_timeout(&self) -> Option<Duration>;
+    fn default_delay(&self) -> Duration;
+    fn default_initial_delay(&self) -> Option<Duration>;
+    fn default_max_attempts(&self) -> Option<u32>;
+    fn is_transient(&self, err: &E) -> bool;
+    fn default_max_transient_failures(&self) -> Option<u32>;
+    fn default_poll_timeout(&self) -> Option<Duration>;
+    fn poll_timeout_error(&self) -> E;
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+    fn on_cancel(&mut self);
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress);
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>>;
+    fn timeout_error(&self) -> E;
+}
+
+/// Marker for the implementation of `LoopWaiter` for all `Waiter`s.
+pub(crate) enum ViaWaiter {}
+
+impl<W, T, E> LoopWaiter<T, E, ViaWaiter> for W
+where
+    W: Waiter<T, E> + ?Sized,
+{
+    type Sleep = BoxedSleep;
+
+    fn name(&self) -> Cow<'static, str> {
+        Waiter::name(self)
+    }
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        Waiter::default_wait_timeout(self)
+    }
+
+    fn default_delay(&self) -> Duration {
+        Waiter::default_delay(self)
+    }
+
+    fn default_initial_delay(&self) -> Option<Duration> {
+        Waiter::default_initial_delay(self)
+    }
+
+    fn default_max_attempts(&self) -> Option<u32> {
+        Waiter::default_max_attempts(self)
+    }
+
+    fn is_transient(&self, err: &E) -> bool {
+        Waiter::is_transient(self, err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        Waiter::default_max_transient_failures(self)
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        Waiter::default_poll_timeout(self)
+    }
+
+    fn poll_timeout_error(&self) -> E {
+        Waiter::poll_timeout_error(self)
+    }
+
+    fn now(&self) -> Instant {
+        Waiter::timer(self).now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxedSleep {
+        Waiter::timer(self).sleep(duration)
+    }
+
+    fn on_cancel(&mut self) {
+        Waiter::on_cancel(self)
+    }
+
+    fn on_wait_event(&mut self, event: Event<'_, T, E>, progress: &Progress) {
+        Waiter::on_wait_event(self, event, progress)
+    }
+
+    fn poll(&mut self) -> impl Future<Output = Result<Option<T>, E>> {
+        Waiter::poll(self)
+    }
+
+    fn timeout_error(&self) -> E {
+        Waiter::timeout_error(self)
+    }
+}
+
+/// Limits on how long to wait.
+///
+/// Waiting stops on whichever limit is reached first.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Limits {
+    /// Time after which no more polls are made, `None` to wait forever.
+    pub deadline: Option<Instant>,
+    /// Maximum number of `poll` calls, `None` for no limit.
+    pub max_attempts: Option<u32>,
+    /// Maximum number of consecutive transient failures, `None` for no limit.
+    pub max_transient_failures: Option<u32>,
+    /// Maximum duration of a single `poll` call, `None` for no limit.
+    pub poll_timeout: Option<Duration>,
+    /// Delay before the first `poll` call.
+    pub initial_delay: Option<Duration>,
+}
+
+impl Limits {
+    /// Limits with the given timeout and the waiter's defaults for the rest.
+    pub fn new<W, T, E, K>(waiter: &W, timeout: Option<Duration>) -> Limits
+    where
+        W: LoopWaiter<T, E, K> + ?Sized,
+    {
+        // A timeout too large to represent is the same as no timeout.
+        let deadline = timeout.and_then(|timeout| waiter.now().checked_add(timeout));
+        Limits::until(waiter, deadline)
+    }
+
+    /// Limits with the given deadline and the waiter's defaults for the rest.
+    pub fn until<W, T, E, K>(waiter: &W, deadline: Option<Instant>) -> Limits
+    where
+        W: LoopWaiter<T, E, K> + ?Sized,
+    {
+        Limits {
+            deadline,
+            max_attempts: waiter.default_max_attempts(),
+            max_transient_failures: waiter.default_max_transient_failures(),
+            poll_timeout: waiter.default_poll_timeout(),
+            initial_delay: waiter.default_initial_delay(),
+        }
+    }
+}
+
+/// Storage for the last transient error.
+trait LastError<E> {
+    fn set(&mut self, err: E);
+    fn take(&mut self) -> Option<E>;
+}
+
+impl<E> LastError<E> for Option<E> {
+    fn set(&mut self, err: E) {
+        *self = Some(err);
+    }
+
+    fn take(&mut self) -> Option<E> {
+        Option::take(self)
+    }
+}
+
+/// Storage that drops transient errors.
+///
+/// Used when the error is never reported, so that the loop does not hold
+/// an `E` across suspension points and `E` does not have to be `Send`.
+struct Discard;
+
+impl<E> LastError<E> for Discard {
+    fn set(&mut self, _err: E) {}
+
+    fn take(&mut self) -> Option<E> {
+        None
+    }
+}
+
+/// Poll `waiter` until it finishes, fails or one of the `limits` is reached.
+///
+/// The `current_state` callback is used to fill `last_state` in errors.
+/// If `cancel` is cancelled, waiting stops immediately, even in the middle of
+/// a `poll` call or a delay.
+pub(crate) async fn wait_loop<W, T, E, K, S, B, F>(
+    waiter: &mut W,
+    limits: Limits,
+    backoff: B,
+    current_state: F,
+    cancel: Option<&CancellationToken>,
+) -> Result<T, WaitError<E, S>>
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+    B: Backoff,
+    F: Fn(&W) -> Option<S>,
+{
+    drive(waiter, limits, backoff, current_state, cancel, None).await
+}
+
+/// Poll `waiter` like `wait_loop`, returning the waiter's own error.
+///
+/// The last transient error is never reported, so `E` does not have to be
+/// `Send` for the future to be `Send`.
+pub(crate) async fn wait_simple<W, T, E, K, B>(
+    waiter: &mut W,
+    limits: Limits,
+    backoff: B,
+) -> Result<T, E>
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+    B: Backoff,
+{
+    match drive(waiter, limits, backoff, |_| None::<()>, None, Discard).await {
+        Ok(result) => Ok(result),
+        Err(err) => Err(into_waiter_error(waiter, err)),
+    }
+}
+
+async fn drive<W, T, E, K, S, B, F, L>(
+    waiter: &mut W,
+    limits: Limits,
+    backoff: B,
+    current_state: F,
+    cancel: Option<&CancellationToken>,
+    last_error: L,
+) -> Result<T, WaitError<E, S>>
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+    B: Backoff,
+    F: Fn(&W) -> Option<S>,
+    L: LastError<E>,
+{
+    #[cfg(feature = "tracing")]
+    let span = crate::trace::wait_span(waiter, &limits);
+
+    let future = async move {
+        let mut stats = Stats {
+            start: waiter.now(),
+            attempts: 0,
+        };
+        stats.notify(waiter, Event::Start);
+        let result = run(
+            waiter,
+            limits,
+            backoff,
+            current_state,
+            cancel,
+            last_error,
+            &mut stats,
+        )
+        .await;
+        let event = match &result {
+            Ok(result) => Event::Success(result),
+            Err(WaitError::Poll(err)) => Event::Error(err),
+            Err(WaitError::Timeout { .. }) => Event::Timeout,
+            Err(WaitError::AttemptsExhausted { .. }) => Event::AttemptsExhausted,
+            Err(WaitError::Cancelled) => {
+                waiter.on_cancel();
+                Event::Cancelled
+            }
+        };
+        stats.notify(waiter, event);
+        result
+    };
+
+    #[cfg(feature = "tracing")]
+    let future = tracing::Instrument::instrument(future, span);
+
+    future.await
+}
+
+/// Statistics of the current waiting loop.
+struct Stats {
+    start: Instant,
+    attempts: u32,
+}
+
+impl Stats {
+    fn elapsed<W, T, E, K>(&self, waiter: &W) -> Duration
+    where
+        W: LoopWaiter<T, E, K> + ?Sized,
+    {
+        waiter.now().saturating_duration_since(self.start)
+    }
+
+    fn notify<W, T, E, K>(&self, waiter: &mut W, event: Event<'_, T, E>)
+    where
+        W: LoopWaiter<T, E, K> + ?Sized,
+    {
+        let progress = Progress::new(self.attempts, self.elapsed(waiter));
+        #[cfg(feature = "tracing")]
+        crate::trace::wait_event(&event, &progress);
+        #[cfg(feature = "metrics")]
+        crate::metrics::record(waiter, &event, &progress);
+        waiter.on_wait_event(event, &progress);
+    }
+}
+
+async fn run<W, T, E, K, S, B, F, L>(
+    waiter: &mut W,
+    limits: Limits,
+    mut backoff: B,
+    current_state: F,
+    cancel: Option<&CancellationToken>,
+    mut last_error: L,
+    stats: &mut Stats,
+) -> Result<T, WaitError<E, S>>
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+    B: Backoff,
+    F: Fn(&W) -> Option<S>,
+    L: LastError<E>,
+{
+    let mut transient_failures = 0;
+    let mut next_delay = limits.initial_delay;
+    loop {
+        if let Some(mut delay) = next_delay {
+            if let Some(deadline) = limits.deadline {
+                // Never sleep past the deadline.
+                delay = delay.min(deadline.saturating_duration_since(waiter.now()));
+            }
+            stats.notify(waiter, Event::Sleep(delay));
+            let sleep = waiter.sleep(delay);
+            if or_cancelled(cancel, sleep).await.is_none() {
+                return Err(WaitError::Cancelled);
+            }
+        }
+
+        // No polls are made once the deadline is reached, not even the first.
+        if limits
+            .deadline
+            .is_some_and(|deadline| waiter.now() >= deadline)
+        {
+            return Err(WaitError::Timeout {
+                elapsed: stats.elapsed(waiter),
+                attempts: stats.attempts,
+                last_state: current_state(waiter),
+                last_error: last_error.take(),
+            });
+        }
+
+        stats.attempts += 1;
+        let poll_timeout = limits
+            .poll_timeout
+            .map(|poll_timeout| match limits.deadline {
+                // Do not let a single poll exceed the overall budget.
+                Some(deadline) => {
+                    poll_timeout.min(deadline.saturating_duration_since(waiter.now()))
+                }
+                None => poll_timeout,
+            });
+        let failure = match or_cancelled(cancel, poll_with_timeout(waiter, poll_timeout)).await {
+            None => return Err(WaitError::Cancelled),
+            Some(Some(Ok(Some(result)))) => {
+                stats.notify(waiter, Event::PollResult(Ok(Some(&result))));
+                return Ok(result);
+            }
+            Some(Some(Ok(None))) => None,
+            Some(Some(Err(err))) => Some(err),
+            Some(None)
+                if limits
+                    .deadline
+                    .is_some_and(|deadline| waiter.now() >= deadline) =>
+            {
+                return Err(WaitError::Timeout {
+                    elapsed: stats.elapsed(waiter),
+                    attempts: stats.attempts,
+                    last_state: current_state(waiter),
+                    last_error: last_error.take(),
+                });
+            }
+            Some(None) => Some(waiter.poll_timeout_error()),
+        };
+        match failure {
+            None => {
+                stats.notify(waiter, Event::PollResult(Ok(None)));
+                transient_failures = 0;
+            }
+            Some(err)
+                if waiter.is_transient(&err)
+                    && limits
+                        .max_transient_failures
+                        .map_or(true, |max| transient_failures < max) =>
+            {
+                stats.notify(waiter, Event::PollResult(Err(&err)));
+                transient_failures += 1;
+                last_error.set(err);
+            }
+            Some(err) => {
+                stats.notify(waiter, Event::PollResult(Err(&err)));
+                return Err(WaitError::Poll(err));
+            }
+        }
+        if limits.max_attempts.is_some_and(|max| stats.attempts >= max) {
+            return Err(WaitError::AttemptsExhausted {
+                elapsed: stats.elapsed(waiter),
+                attempts: stats.attempts,
+                last_state: current_state(waiter),
+                last_error: last_error.take(),
+            });
+        }
+
+        next_delay = Some(backoff.next_delay());
+    }
+}
+
+/// Call `poll`, giving up after `poll_timeout` if it is set.
+///
+/// Returns `None` if the call timed out.
+async fn poll_with_timeout<W, T, E, K>(
+    waiter: &mut W,
+    poll_timeout: Option<Duration>,
+) -> Option<Result<Option<T>, E>>
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+{
+    match poll_timeout {
+        Some(poll_timeout) => {
+            let sleep = waiter.sleep(poll_timeout);
+            timeout(sleep, waiter.poll()).await
+        }
+        None => Some(waiter.poll().await),
+    }
+}
+
+/// Convert a detailed error into the waiter's own error.
+fn into_waiter_error<W, T, E, K, S>(waiter: &W, err: WaitError<E, S>) -> E
+where
+    W: LoopWaiter<T, E, K> + ?Sized,
+{
+    match err {
+        WaitError::Poll(err) => err,
+        _ => waiter.timeout_error(),
+    }
+}
@@ -0,0 +1,134 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Backoff strategies.
+
+use std::time::Duration;
+
+use waiter::backoff::{Capped, Constant, Exponential, Fibonacci, Jitter, Jittered, Linear};
+use waiter::Backoff;
+
+const SEEDS: std::ops::Range<u64> = 0..20;
+
+fn delays<B: Backoff>(mut backoff: B, count: usize) -> Vec<Duration> {
+    (0..count).map(|_| backoff.next_delay()).collect()
+}
+
+fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+fn jittered(jitter: Jitter, seed: u64) -> Jittered<Constant> {
+    Constant::new(secs(10)).with_jitter(jitter).with_seed(seed)
+}
+
+#[test]
+fn constant_repeats_the_delay() {
+    assert_eq!(delays(Constant::new(secs(3)), 3), [secs(3); 3]);
+}
+
+#[test]
+fn linear_adds_the_step() {
+    let backoff = Linear::new(secs(1), secs(2));
+    assert_eq!(delays(backoff, 4), [secs(1), secs(3), secs(5), secs(7)]);
+}
+
+#[test]
+fn exponential_multiplies_by_the_factor() {
+    let backoff = Exponential::new(secs(1));
+    assert_eq!(delays(backoff, 4), [secs(1), secs(2), secs(4), secs(8)]);
+
+    let backoff = Exponential::new(secs(2)).with_factor(1.5);
+    assert_eq!(
+        delays(backoff, 3),
+        [secs(2), secs(3), Duration::from_millis(4500)]
+    );
+}
+
+#[test]
+fn exponential_saturates() {
+    let mut backoff = Exponential::new(Duration::MAX / 4);
+    assert_eq!(backoff.next_delay(), Duration::MAX / 4);
+    backoff.next_delay();
+    assert_eq!(backoff.next_delay(), Duration::MAX);
+    assert_eq!(backoff.next_delay(), Duration::MAX);
+}
+
+#[test]
+#[should_panic(expected = "at least 1.0")]
+fn exponential_rejects_shrinking_factor() {
+    let _ = Exponential::new(secs(1)).with_factor(0.5);
+}
+
+#[test]
+#[should_panic(expected = "must be finite")]
+fn exponential_rejects_infinite_factor() {
+    let _ = Exponential::new(secs(1)).with_factor(f64::INFINITY);
+}
+
+#[test]
+fn fibonacci_follows_the_sequence() {
+    let backoff = Fibonacci::new(secs(2));
+    assert_eq!(
+        delays(backoff, 6),
+        [secs(2), secs(2), secs(4), secs(6), secs(10), secs(16)]
+    );
+
+    let mut backoff = Fibonacci::new(Duration::MAX / 2);
+    for _ in 0..5 {
+        backoff.next_delay();
+    }
+    assert_eq!(backoff.next_delay(), Duration::MAX);
+}
+
+#[test]
+fn capped_limits_the_delays() {
+    let backoff = Exponential::new(secs(1)).capped(secs(5));
+    assert_eq!(
+        delays(backoff, 5),
+        [secs(1), secs(2), secs(4), secs(5), secs(5)]
+    );
+
+    let backoff = Capped::new(Linear::new(secs(1), secs(1)), secs(2));
+    assert_eq!(backoff.into_inner(), Linear::new(secs(1), secs(1)));
+}
+
+#[test]
+fn full_jitter_stays_below_the_delay() {
+    for seed in SEEDS {
+        let delays = delays(jittered(Jitter::Full, seed), 50);
+        assert!(delays.iter().all(|d| *d <= secs(10)));
+        assert!(delays.iter().any(|d| *d < secs(5)));
+    }
+}
+
+#[test]
+fn equal_jitter_keeps_half_of_the_delay() {
+    for seed in SEEDS {
+        let delays = delays(jittered(Jitter::Equal, seed), 50);
+        assert!(delays.iter().all(|d| (secs(5)..=secs(10)).contains(d)));
+        assert!(delays.iter().any(|d| *d < secs(10)));
+    }
+}
+
+#[test]
+fn decorrelated_jitter_stays_within_bounds() {
+    let base = secs(1);
+    let max = secs(20);
+    for seed in SEEDS {
+        let backoff = Constant::new(base)
+            .with_jitter(Jitter::Decorrelated { max })
+            .with_seed(seed);
+        let delays = delays(backoff, 100);
+        assert!(delays.iter().all(|d| (base..=max).contains(d)));
+        for pair in delays.windows(2) {
+            assert!(pair[1] <= pair[0] * 3, "{pair:?}");
+        }
+        // The limit is applied before the next delay is computed, so the
+        // delays do not get stuck at the maximum.
+        assert!(delays[20..].iter().any(|d| *d < max));
+    }
+}
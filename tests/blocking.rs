@@ -0,0 +1,204 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting loop of `BlockingWaiter`.
+
+use std::time::{Duration, Instant};
+
+use waiter::blocking::{Blocking, BlockingWaiter, BlockingWaiterWithState};
+use waiter::observer::{Observer, Progress};
+use waiter::{CancellationToken, TimedOut, WaitError, WaitOptions, Waiter, WaiterCurrentState};
+
+const DELAY: Duration = Duration::from_millis(5);
+
+/// Waiter finishing after the given number of polls, 0 for never.
+#[derive(Debug, Default)]
+struct Counted {
+    polls: u32,
+    ready_after: u32,
+}
+
+impl Counted {
+    fn new(ready_after: u32) -> Counted {
+        Counted {
+            polls: 0,
+            ready_after,
+        }
+    }
+}
+
+impl BlockingWaiter<u32, TimedOut> for Counted {
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        Some(Duration::from_secs(10))
+    }
+
+    fn default_delay(&self) -> Duration {
+        DELAY
+    }
+
+    fn poll(&mut self) -> Result<Option<u32>, TimedOut> {
+        self.polls += 1;
+        Ok((self.polls == self.ready_after).then_some(self.polls))
+    }
+
+    fn timeout_error(&self) -> TimedOut {
+        TimedOut
+    }
+}
+
+impl WaiterCurrentState<u32> for Counted {
+    fn waiter_current_state(&self) -> &u32 {
+        &self.polls
+    }
+}
+
+#[derive(Debug, Default)]
+struct Recorder {
+    polls: u32,
+    sleeps: u32,
+    result: Option<u32>,
+    last_state: Option<u32>,
+}
+
+impl Observer<u32, TimedOut, u32> for &mut Recorder {
+    fn on_poll_result(
+        &mut self,
+        _result: Result<Option<&u32>, &TimedOut>,
+        _progress: &Progress,
+        state: Option<&u32>,
+    ) {
+        self.polls += 1;
+        self.last_state = state.copied();
+    }
+
+    fn on_sleep(&mut self, _delay: Duration, _progress: &Progress, _state: Option<&u32>) {
+        self.sleeps += 1;
+    }
+
+    fn on_success(&mut self, result: &u32, _progress: &Progress) {
+        self.result = Some(*result);
+    }
+}
+
+#[test]
+fn blocking_wait_returns_result() {
+    assert_eq!(Counted::new(3).wait(), Ok(3));
+}
+
+#[test]
+fn blocking_wait_attempts_stops_after_limit() {
+    let result = Counted::new(0).wait_attempts(3);
+    assert!(
+        matches!(
+            result,
+            Err(WaitError::AttemptsExhausted { attempts: 3, .. })
+        ),
+        "{result:?}"
+    );
+}
+
+#[test]
+fn blocking_wait_for_with_state_times_out() {
+    let result = Counted::new(0).wait_for_with_state(Duration::from_millis(30));
+    match result {
+        Err(WaitError::Timeout {
+            attempts,
+            last_state,
+            ..
+        }) => {
+            assert!(attempts > 0);
+            assert_eq!(last_state, Some(attempts));
+        }
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn blocking_wait_until_past_deadline_makes_no_polls() {
+    let deadline = Instant::now();
+    let mut recorder = Recorder::default();
+    let waiter = Counted::new(1).with_state_observer(&mut recorder);
+    assert_eq!(waiter.wait_until(deadline), Err(TimedOut));
+    assert_eq!(recorder.polls, 0);
+}
+
+#[test]
+fn blocking_observer_sees_events() {
+    let mut recorder = Recorder::default();
+    let waiter = Counted::new(3).with_state_observer(&mut recorder);
+    assert_eq!(waiter.wait(), Ok(3));
+    assert_eq!(recorder.polls, 3);
+    assert_eq!(recorder.sleeps, 2);
+    assert_eq!(recorder.result, Some(3));
+    assert_eq!(recorder.last_state, Some(3));
+}
+
+#[test]
+fn blocking_wait_with_options() {
+    let result = Counted::new(0).wait_with(WaitOptions::new().with_max_attempts(2));
+    assert!(
+        matches!(
+            result,
+            Err(WaitError::AttemptsExhausted { attempts: 2, .. })
+        ),
+        "{result:?}"
+    );
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let mut recorder = Recorder::default();
+    let waiter = Counted::new(1).with_state_observer(&mut recorder);
+    let result = waiter.wait_with(WaitOptions::new().with_cancellation_token(token));
+    assert_eq!(result, Err(WaitError::Cancelled));
+    assert_eq!(recorder.polls, 0);
+}
+
+#[test]
+fn async_adapter_waits_with_the_async_loop() {
+    let mut recorder = Recorder::default();
+    let waiter = Counted::new(3)
+        .with_state_observer(&mut recorder)
+        .into_async();
+    assert_eq!(waiter.waiter_current_state(), &0);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .build()
+        .unwrap();
+    assert_eq!(runtime.block_on(waiter.wait()), Ok(3));
+    assert_eq!(recorder.polls, 3);
+    assert_eq!(recorder.result, Some(3));
+    assert_eq!(recorder.last_state, Some(3));
+}
+
+#[test]
+fn blocking_and_async_adapters_round_trip() {
+    let mut recorder = Recorder::default();
+    let waiter = Blocking::new(
+        Counted::new(2)
+            .with_state_observer(&mut recorder)
+            .into_async(),
+    );
+    assert_eq!(BlockingWaiter::default_delay(&waiter), DELAY);
+    assert_eq!(waiter.waiter_current_state(), &0);
+
+    let result = waiter.wait_for_with_state(Duration::from_secs(10));
+    assert_eq!(result, Ok(2));
+    // Events pass through both adapters.
+    assert_eq!(recorder.polls, 2);
+    assert_eq!(recorder.sleeps, 1);
+    assert_eq!(recorder.result, Some(2));
+
+    let waiter = Blocking::new(Counted::new(0).into_async());
+    let result = waiter.wait_for_attempts(Duration::from_secs(10), 2);
+    assert!(
+        matches!(
+            result,
+            Err(WaitError::AttemptsExhausted { attempts: 2, .. })
+        ),
+        "{result:?}"
+    );
+}
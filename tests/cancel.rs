@@ -0,0 +1,130 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Cancellation tokens.
+
+mod common;
+
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{CancellationToken, WaitError, Waiter};
+
+use common::{block_on, secs, Error};
+
+#[derive(Default)]
+struct CountingWaker {
+    wakes: AtomicUsize,
+}
+
+impl Wake for CountingWaker {
+    fn wake(self: Arc<Self>) {
+        self.wakes.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn dropped_futures_release_their_wakers() {
+    let token = CancellationToken::new();
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(Arc::clone(&counter));
+    let mut cx = Context::from_waker(&waker);
+
+    for _ in 0..100 {
+        let mut cancelled = pin!(token.cancelled());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+        // Polling again with the same waker does not register it twice.
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+    }
+
+    // Only `counter` itself and `waker` are left.
+    assert_eq!(Arc::strong_count(&counter), 2);
+    token.cancel();
+    assert_eq!(counter.wakes.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn cancel_wakes_pending_futures() {
+    let token = CancellationToken::new();
+    let counter = Arc::new(CountingWaker::default());
+    let waker = Waker::from(Arc::clone(&counter));
+    let mut cx = Context::from_waker(&waker);
+
+    let mut first = pin!(token.cancelled());
+    let mut second = pin!(token.cancelled());
+    assert!(first.as_mut().poll(&mut cx).is_pending());
+    assert!(second.as_mut().poll(&mut cx).is_pending());
+
+    token.cancel();
+    assert_eq!(counter.wakes.load(Ordering::SeqCst), 2);
+    assert!(first.as_mut().poll(&mut cx).is_ready());
+    assert!(second.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(Arc::strong_count(&counter), 2);
+}
+
+#[test]
+fn cancel_during_sleep_stops_waiting() {
+    let clock = VirtualClock::manual();
+    let waiter =
+        ScriptedWaiter::<u32, Error>::new([Step::Pending, Step::Done(1)]).with_clock(clock.clone());
+    let token = CancellationToken::new();
+
+    let mut wait = pin!(waiter.clone().wait_cancellable(token.clone()));
+    let result = block_on(poll_fn(|cx| {
+        if let Poll::Ready(result) = wait.as_mut().poll(cx) {
+            return Poll::Ready(result);
+        }
+        // The first poll has been made, the loop is sleeping.
+        waiter.assert_polls(1);
+        clock.assert_sleeps(&[secs(1)]);
+        token.cancel();
+        // Cancellation wins even if the sleep is over by now.
+        clock.advance(secs(1));
+        wait.as_mut().poll(cx)
+    }));
+    assert_eq!(result, Err(WaitError::Cancelled));
+    assert!(waiter.is_cancelled());
+    waiter.assert_polls(1);
+    assert_eq!(waiter.remaining(), 1);
+}
+
+#[test]
+fn wait_for_cancellable_finishes_without_cancellation() {
+    let clock = VirtualClock::new();
+    let waiter =
+        ScriptedWaiter::<u32, Error>::new([Step::Pending, Step::Done(1)]).with_clock(clock.clone());
+    let token = CancellationToken::new();
+
+    let result = block_on(waiter.clone().wait_for_cancellable(secs(10), token.clone()));
+    assert_eq!(result, Ok(1));
+    assert!(!waiter.is_cancelled());
+
+    // Cancelling afterwards has no effect on the finished wait.
+    token.cancel();
+    waiter.assert_polls(2);
+    assert!(!waiter.is_cancelled());
+}
+
+#[test]
+fn wait_for_cancellable_times_out() {
+    let clock = VirtualClock::new();
+    let waiter = ScriptedWaiter::<u32, Error>::new([]).with_clock(clock.clone());
+
+    let result = block_on(
+        waiter
+            .clone()
+            .wait_for_cancellable(secs(3), CancellationToken::new()),
+    );
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 3, .. })
+    ));
+    assert!(!waiter.is_cancelled());
+}
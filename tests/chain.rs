@@ -0,0 +1,101 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for waiters in sequence with `WaitChain`.
+
+mod common;
+
+use std::any::type_name;
+
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{Timer, WaitChain, WaitError, WaiterExt};
+
+use common::{block_on, secs, Error};
+
+type Second = ScriptedWaiter<u64, Error>;
+
+fn scripted<T>(clock: &VirtualClock, steps: Vec<Step<T, Error>>) -> ScriptedWaiter<T, Error> {
+    ScriptedWaiter::new(steps).with_clock(clock.clone())
+}
+
+#[test]
+fn stages_receive_previous_results() {
+    let clock = VirtualClock::new();
+    let first = scripted(&clock, vec![Step::Pending, Step::Done(2)]);
+    let second_clock = clock.clone();
+
+    let chain = first
+        .then(move |value: u32| scripted(&second_clock, vec![Step::Done(u64::from(value) * 10)]));
+    assert_eq!(block_on(chain.wait()), Ok(20));
+    assert_eq!(clock.elapsed(), secs(1));
+}
+
+#[test]
+fn later_stages_inherit_the_deadline() {
+    let clock = VirtualClock::new();
+    let first = scripted(&clock, vec![Step::Pending, Step::Pending, Step::Done(1)]);
+    let second_clock = clock.clone();
+    let deadline = clock.now() + secs(5);
+
+    let chain = WaitChain::new(first)
+        .then(move |_: u32| scripted::<u64>(&second_clock, vec![]))
+        .with_deadline(deadline);
+    let err = block_on(chain.wait()).unwrap_err();
+    assert_eq!(err.stage(), 1);
+    assert_eq!(err.name(), type_name::<Second>());
+    // The second stage polls at 2, 3 and 4 seconds.
+    assert!(matches!(
+        err.error(),
+        WaitError::Timeout { attempts: 3, .. }
+    ));
+    assert_eq!(clock.elapsed(), secs(5));
+}
+
+#[test]
+fn timeout_is_not_restarted_by_later_stages() {
+    let clock = VirtualClock::new();
+    let first = scripted(&clock, vec![Step::Pending, Step::Pending, Step::Done(1)]);
+    let second_clock = clock.clone();
+
+    let chain = WaitChain::new(first)
+        .then(move |_: u32| scripted::<u64>(&second_clock, vec![]))
+        .with_timeout(secs(3));
+    let err = block_on(chain.wait()).unwrap_err();
+    assert_eq!(err.stage(), 1);
+    assert_eq!(clock.elapsed(), secs(3));
+}
+
+#[test]
+fn failed_first_stage_is_identified() {
+    let clock = VirtualClock::new();
+    let first = scripted::<u32>(&clock, vec![]);
+    let second_clock = clock.clone();
+
+    let chain = first
+        .then(move |_: u32| scripted::<u64>(&second_clock, vec![Step::Done(1)]))
+        .with_timeout(secs(2));
+    let err = block_on(chain.wait()).unwrap_err();
+    assert_eq!(err.stage(), 0);
+    assert_eq!(err.name(), type_name::<ScriptedWaiter<u32, Error>>());
+    assert!(err.error().is_timeout());
+}
+
+#[test]
+fn without_time_limit_stages_use_their_own_timeouts() {
+    let clock = VirtualClock::new();
+    let first =
+        scripted(&clock, vec![Step::Pending, Step::Pending, Step::Done(1)]).with_timeout(secs(3));
+    let second_clock = clock.clone();
+
+    let chain = WaitChain::new(first)
+        .then(move |_: u32| scripted::<u64>(&second_clock, vec![]).with_timeout(secs(3)));
+    let err = block_on(chain.wait()).unwrap_err();
+    assert_eq!(err.stage(), 1);
+    assert!(err.into_error().is_timeout());
+    // The first stage finishes after 2 seconds, then the second one waits
+    // for its full timeout.
+    assert_eq!(clock.elapsed(), secs(5));
+}
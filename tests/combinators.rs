@@ -0,0 +1,174 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Combinators of `WaiterExt`.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use waiter::observer::{Observer, Progress};
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{DynTimer, WaitError, WaitOptions, Waiter, WaiterExt};
+
+use common::{block_on, secs, Error};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Wrapped(Error);
+
+fn scripted<T>(steps: Vec<Step<T, Error>>) -> ScriptedWaiter<T, Error> {
+    ScriptedWaiter::new(steps)
+        .with_transient(|err| *err == Error::Transient)
+        .with_clock(VirtualClock::new())
+}
+
+#[test]
+fn map_err_keeps_transient_errors() {
+    let waiter = scripted(vec![Step::Err(Error::Transient), Step::Done(1)]);
+    let result = block_on(waiter.clone().map_err(Wrapped).wait_attempts(5));
+    assert_eq!(result, Ok(1));
+    waiter.assert_polls(2);
+
+    let waiter = scripted::<u32>(vec![Step::Err(Error::Fatal), Step::Done(1)]);
+    let result = block_on(waiter.clone().map_err(Wrapped).wait_attempts(5));
+    assert_eq!(result, Err(WaitError::Poll(Wrapped(Error::Fatal))));
+    waiter.assert_polls(1);
+}
+
+/// Waiter hanging on the first `poll` call and finishing on the second.
+struct Hanging {
+    polls: u32,
+    clock: VirtualClock,
+}
+
+#[async_trait]
+impl Waiter<u32, Error> for Hanging {
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    fn default_delay(&self) -> Duration {
+        secs(1)
+    }
+
+    fn is_transient(&self, err: &Error) -> bool {
+        *err == Error::PollTimedOut
+    }
+
+    fn poll_timeout_error(&self) -> Error {
+        Error::PollTimedOut
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        &self.clock
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, Error> {
+        self.polls += 1;
+        if self.polls == 1 {
+            std::future::pending::<()>().await;
+        }
+        Ok(Some(self.polls))
+    }
+
+    fn timeout_error(&self) -> Error {
+        Error::TimedOut
+    }
+}
+
+#[test]
+fn map_err_keeps_transient_poll_timeouts() {
+    let waiter = Hanging {
+        polls: 0,
+        clock: VirtualClock::new(),
+    };
+    let options = WaitOptions::new()
+        .with_poll_timeout(secs(5))
+        .with_max_attempts(3);
+    let result = block_on(waiter.map_err(Wrapped).wait_with(options));
+    assert_eq!(result, Ok(2));
+}
+
+#[test]
+fn map_err_with_classifies_transformed_errors() {
+    let waiter = scripted(vec![Step::Err(Error::Transient), Step::Done(1)]);
+    let mapped = waiter
+        .clone()
+        .map_err_with(Wrapped, |err| *err == Wrapped(Error::Transient));
+    assert_eq!(block_on(mapped.wait_attempts(5)), Ok(1));
+    waiter.assert_polls(2);
+}
+
+#[test]
+fn and_then_errors_are_fatal() {
+    let waiter = scripted(vec![Step::Err(Error::Transient), Step::Done(())]);
+    let result = block_on(
+        waiter
+            .clone()
+            .map_err_with(Wrapped, |err| *err == Wrapped(Error::Transient))
+            .and_then(|()| Err::<(), _>(Wrapped(Error::Transient)))
+            .wait_attempts(5),
+    );
+    assert_eq!(result, Err(WaitError::Poll(Wrapped(Error::Transient))));
+    // Never polled again after returning the result.
+    waiter.assert_polls(2);
+}
+
+#[test]
+fn and_then_keeps_retrying_transient_poll_errors() {
+    let waiter = scripted(vec![Step::Err(Error::Transient), Step::Done(20)]);
+    let result = block_on(
+        waiter
+            .clone()
+            .and_then(|value| Ok::<_, Error>(value + 1))
+            .wait_attempts(5),
+    );
+    assert_eq!(result, Ok(21));
+    waiter.assert_polls(2);
+}
+
+#[derive(Clone, Default)]
+struct Recorder {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl<T, E> Observer<T, E> for Recorder
+where
+    T: std::fmt::Debug,
+    E: std::fmt::Debug,
+{
+    fn on_success(&mut self, result: &T, _progress: &Progress) {
+        let event = format!("success {:?}", result);
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn on_error(&mut self, err: &E, _progress: &Progress, _state: Option<&()>) {
+        let event = format!("error {:?}", err);
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn observers_attached_last_see_transformed_results() {
+    let recorder = Recorder::default();
+    let waiter = scripted(vec![Step::Done(1)])
+        .map(|value| value * 10)
+        .with_observer(recorder.clone());
+    assert_eq!(block_on(waiter.wait_attempts(1)), Ok(10));
+
+    let waiter = scripted::<u32>(vec![Step::Err(Error::Fatal)])
+        .map_err(Wrapped)
+        .with_observer(recorder.clone());
+    assert_eq!(
+        block_on(waiter.wait_attempts(1)),
+        Err(WaitError::Poll(Wrapped(Error::Fatal)))
+    );
+
+    let events = recorder.events.lock().unwrap().clone();
+    assert_eq!(events, ["success 10", "error Wrapped(Fatal)"]);
+}
@@ -0,0 +1,65 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Helpers shared by the integration tests.
+
+// Every test crate uses its own subset of the helpers.
+#![allow(dead_code)]
+
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::task::Poll;
+use std::time::Duration;
+
+use waiter::testing::VirtualClock;
+use waiter::TimedOut;
+
+/// Error of the waiters under test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Transient,
+    Fatal,
+    TimedOut,
+    PollTimedOut,
+}
+
+impl From<TimedOut> for Error {
+    fn from(_: TimedOut) -> Error {
+        Error::TimedOut
+    }
+}
+
+/// Single-threaded runtime without I/O or timers.
+pub fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap()
+}
+
+/// Run `future` to completion on a fresh runtime.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    runtime().block_on(future)
+}
+
+pub fn secs(secs: u64) -> Duration {
+    Duration::from_secs(secs)
+}
+
+/// Run `future`, advancing the manual `clock` by a second whenever it is
+/// pending.
+///
+/// Lets several waiting loops share one clock: with an automatic clock the
+/// first loop to sleep would run to completion before the others start.
+pub async fn ticking<F: Future>(clock: &VirtualClock, future: F) -> F::Output {
+    let mut future = pin!(future);
+    poll_fn(|cx| loop {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(output);
+        }
+        clock.advance(secs(1));
+    })
+    .await
+}
@@ -0,0 +1,99 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for several waiters with `join_all`.
+
+mod common;
+
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{join_all, Timer, WaitError};
+
+use common::{block_on, secs, ticking, Error};
+
+fn scripted(clock: &VirtualClock, steps: Vec<Step<u32, Error>>) -> ScriptedWaiter<u32, Error> {
+    ScriptedWaiter::new(steps).with_clock(clock.clone())
+}
+
+#[test]
+fn results_keep_the_order_of_waiters() {
+    let clock = VirtualClock::manual();
+    let slow = scripted(&clock, vec![Step::Pending, Step::Pending, Step::Done(1)]);
+    let fast = scripted(&clock, vec![Step::Done(2)]);
+    let failing = scripted(&clock, vec![Step::Pending, Step::Err(Error::Fatal)]);
+
+    let join = join_all([slow.clone(), fast.clone(), failing.clone()]);
+    let results = block_on(ticking(&clock, join.wait()));
+    assert_eq!(results, [Ok(1), Ok(2), Err(WaitError::Poll(Error::Fatal))]);
+    // Finished waiters are not polled again.
+    slow.assert_polls(3);
+    fast.assert_polls(1);
+    failing.assert_polls(2);
+    assert_eq!(clock.elapsed(), secs(2));
+}
+
+#[test]
+fn with_timeout_sets_a_shared_deadline() {
+    let clock = VirtualClock::manual();
+    let frequent = scripted(&clock, vec![]);
+    let rare = scripted(&clock, vec![]).with_delay(secs(2));
+
+    let join = join_all([frequent.clone(), rare.clone()]).with_timeout(secs(3));
+    let results = block_on(ticking(&clock, join.wait()));
+    assert!(
+        results
+            .iter()
+            .all(|result| matches!(result, Err(WaitError::Timeout { .. }))),
+        "{results:?}"
+    );
+    // Polls at 0, 1 and 2 seconds and at 0 and 2 seconds respectively.
+    frequent.assert_polls(3);
+    rare.assert_polls(2);
+    assert_eq!(clock.elapsed(), secs(3));
+}
+
+#[test]
+fn with_deadline_sets_a_shared_deadline() {
+    let clock = VirtualClock::manual();
+    let pending = scripted(&clock, vec![]);
+    let finishing = scripted(&clock, vec![Step::Pending, Step::Done(1)]).with_delay(secs(4));
+
+    let join = join_all([pending.clone(), finishing.clone()]).with_deadline(clock.now() + secs(2));
+    let results = block_on(ticking(&clock, join.wait()));
+    assert!(matches!(
+        results[0],
+        Err(WaitError::Timeout { attempts: 2, .. })
+    ));
+    // The delay of the second waiter is cut short by the deadline.
+    assert!(matches!(
+        results[1],
+        Err(WaitError::Timeout { attempts: 1, .. })
+    ));
+    finishing.assert_polls(1);
+    assert_eq!(clock.elapsed(), secs(2));
+}
+
+#[test]
+fn fail_fast_cancels_unfinished_waiters() {
+    let clock = VirtualClock::manual();
+    let finished = scripted(&clock, vec![Step::Done(1)]);
+    let pending = scripted(&clock, vec![]);
+    let failing = scripted(&clock, vec![Step::Pending, Step::Err(Error::Fatal)]);
+
+    let join = join_all([finished.clone(), pending.clone(), failing.clone()]).with_fail_fast(true);
+    let results = block_on(ticking(&clock, join.wait()));
+    assert_eq!(
+        results,
+        [
+            Ok(1),
+            Err(WaitError::Cancelled),
+            Err(WaitError::Poll(Error::Fatal))
+        ]
+    );
+    assert!(!finished.is_cancelled());
+    assert!(pending.is_cancelled());
+    assert!(!failing.is_cancelled());
+    pending.assert_polls(2);
+}
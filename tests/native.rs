@@ -0,0 +1,74 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Native waiters.
+
+mod common;
+
+use std::time::Duration;
+
+use waiter::native::NativeWaiter;
+use waiter::testing::VirtualClock;
+use waiter::{TimedOut, WaitError, Waiter};
+
+use common::block_on;
+
+/// Waiter finishing on the given attempt.
+struct Countdown {
+    left: u32,
+    clock: VirtualClock,
+}
+
+impl NativeWaiter<u32, TimedOut> for Countdown {
+    type Timer = VirtualClock;
+
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        Some(Duration::from_secs(10))
+    }
+
+    fn default_delay(&self) -> Duration {
+        Duration::from_secs(2)
+    }
+
+    fn timer(&self) -> &VirtualClock {
+        &self.clock
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, TimedOut> {
+        self.left -= 1;
+        Ok((self.left == 0).then_some(42))
+    }
+
+    fn timeout_error(&self) -> TimedOut {
+        TimedOut
+    }
+}
+
+#[test]
+fn native_waiter_uses_its_timer() {
+    let clock = VirtualClock::new();
+    let waiter = Countdown {
+        left: 3,
+        clock: clock.clone(),
+    };
+    assert_eq!(block_on(waiter.wait()), Ok(42));
+    clock.assert_sleeps(&[Duration::from_secs(2), Duration::from_secs(2)]);
+}
+
+#[test]
+fn boxed_native_waiter_times_out() {
+    let clock = VirtualClock::new();
+    let waiter = Countdown {
+        left: 100,
+        clock: clock.clone(),
+    };
+    let result = block_on(waiter.boxed().wait_detailed());
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 5, .. })
+    ));
+    assert_eq!(clock.elapsed(), Duration::from_secs(10));
+}
@@ -0,0 +1,42 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Options of `Waiter::wait_with`.
+
+mod common;
+
+use std::time::Duration;
+
+use waiter::backoff::Jitter;
+use waiter::testing::{ScriptedWaiter, VirtualClock};
+use waiter::{TimedOut, WaitError, WaitOptions, Waiter};
+
+use common::block_on;
+
+fn jittered_sleeps(seed: u64) -> Vec<Duration> {
+    let clock = VirtualClock::new();
+    let waiter = ScriptedWaiter::<(), TimedOut>::new([]).with_clock(clock.clone());
+    let options = WaitOptions::new()
+        .with_delay(Duration::from_secs(10))
+        .with_max_attempts(8)
+        .with_jitter(Jitter::Full)
+        .with_jitter_seed(seed);
+    let result = block_on(waiter.wait_with(options));
+    assert!(matches!(
+        result,
+        Err(WaitError::AttemptsExhausted { attempts: 8, .. })
+    ));
+    clock.sleeps()
+}
+
+#[test]
+fn jitter_seed_makes_delays_reproducible() {
+    let sleeps = jittered_sleeps(42);
+    assert_eq!(sleeps.len(), 7);
+    assert!(sleeps.iter().all(|sleep| *sleep <= Duration::from_secs(10)));
+    assert_eq!(sleeps, jittered_sleeps(42));
+    assert_ne!(sleeps, jittered_sleeps(43));
+}
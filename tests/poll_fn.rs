@@ -0,0 +1,90 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiters built from closures with `poll_fn`.
+
+mod common;
+
+use std::future::{ready, Ready};
+use std::time::Duration;
+
+use waiter::testing::{Clocked, VirtualClock};
+use waiter::{poll_fn, TimedOut, Waiter};
+
+use common::{block_on, secs, Error};
+
+/// Closure finishing on the given call, 0 for never.
+fn countdown<E>(ready_on: u32) -> impl FnMut() -> Ready<Result<Option<u32>, E>> + Send {
+    let mut calls = 0;
+    move || {
+        calls += 1;
+        ready(Ok((calls == ready_on).then_some(calls)))
+    }
+}
+
+#[test]
+fn defaults_wait_forever_with_one_second_delay() {
+    let waiter = poll_fn(countdown::<TimedOut>(3));
+    assert_eq!(waiter.default_wait_timeout(), None);
+    assert_eq!(waiter.default_delay(), secs(1));
+
+    let clock = VirtualClock::new();
+    let result = block_on(Clocked::new(waiter, clock.clone()).wait());
+    assert_eq!(result, Ok(3));
+    clock.assert_sleeps(&[secs(1), secs(1)]);
+}
+
+#[test]
+fn with_delay_changes_the_delay() {
+    let waiter = poll_fn(countdown::<TimedOut>(3)).with_delay(secs(2));
+    let clock = VirtualClock::new();
+    let result = block_on(Clocked::new(waiter, clock.clone()).wait());
+    assert_eq!(result, Ok(3));
+    clock.assert_sleeps(&[secs(2), secs(2)]);
+}
+
+#[test]
+fn with_timeout_uses_default_timeout_error() {
+    let waiter = poll_fn(countdown::<TimedOut>(0)).with_timeout(secs(3));
+    assert_eq!(waiter.default_wait_timeout(), Some(secs(3)));
+    let clock = VirtualClock::new();
+    let result = block_on(Clocked::new(waiter, clock.clone()).wait());
+    assert_eq!(result, Err(TimedOut));
+    assert_eq!(clock.elapsed(), secs(3));
+
+    // Any error convertible from `TimedOut` works.
+    let waiter = poll_fn(countdown::<Error>(0)).with_timeout(secs(3));
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(result, Err(Error::TimedOut));
+}
+
+#[test]
+fn with_timeout_error_creates_the_error() {
+    let waiter = poll_fn(countdown(0))
+        .with_timeout(secs(3))
+        .with_timeout_error(|| Error::Fatal);
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(result, Err(Error::Fatal));
+
+    let waiter = poll_fn(countdown(0)).with_timeout_error(|| Error::Fatal);
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait_for(Duration::ZERO));
+    assert_eq!(result, Err(Error::Fatal));
+}
+
+#[test]
+fn errors_are_propagated() {
+    let mut calls = 0;
+    let waiter = poll_fn(move || {
+        calls += 1;
+        ready(if calls < 2 {
+            Ok(None)
+        } else {
+            Err(Error::Fatal)
+        })
+    });
+    let result: Result<u32, Error> = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(result, Err(Error::Fatal));
+}
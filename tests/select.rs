@@ -0,0 +1,88 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting for the first of several waiters with `select_ok`.
+
+mod common;
+
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{select_ok, Timer, WaitError};
+
+use common::{block_on, secs, ticking, Error};
+
+fn scripted(clock: &VirtualClock, steps: Vec<Step<u32, Error>>) -> ScriptedWaiter<u32, Error> {
+    ScriptedWaiter::new(steps).with_clock(clock.clone())
+}
+
+#[test]
+fn winner_index_skips_failed_waiters() {
+    let clock = VirtualClock::manual();
+    let failing = scripted(&clock, vec![Step::Err(Error::Fatal)]);
+    let winner = scripted(&clock, vec![Step::Pending, Step::Done(5)]);
+
+    let select = select_ok([failing, winner]);
+    let result = block_on(ticking(&clock, select.wait()));
+    assert_eq!(result, Ok((5, 1)));
+}
+
+#[test]
+fn losers_are_cancelled() {
+    let clock = VirtualClock::manual();
+    let pending = scripted(&clock, vec![]);
+    let winner = scripted(&clock, vec![Step::Pending, Step::Done(5)]);
+    let slower = scripted(
+        &clock,
+        vec![Step::Pending, Step::Pending, Step::Pending, Step::Done(7)],
+    );
+
+    let select = select_ok([pending.clone(), winner.clone(), slower.clone()]);
+    let result = block_on(ticking(&clock, select.wait()));
+    assert_eq!(result, Ok((5, 1)));
+    assert!(pending.is_cancelled());
+    assert!(!winner.is_cancelled());
+    assert!(slower.is_cancelled());
+    // Nobody is polled once the winner has finished, even in the same round.
+    pending.assert_polls(2);
+    slower.assert_polls(1);
+    assert_eq!(slower.remaining(), 3);
+}
+
+#[test]
+fn all_failed_keeps_errors_in_order() {
+    let clock = VirtualClock::manual();
+    let pending = scripted(&clock, vec![]);
+    let failing = scripted(&clock, vec![Step::Pending, Step::Err(Error::Fatal)]);
+
+    let select = select_ok([pending, failing]).with_timeout(secs(3));
+    let err = block_on(ticking(&clock, select.wait())).unwrap_err();
+    assert!(matches!(
+        err.errors(),
+        [
+            WaitError::Timeout { attempts: 3, .. },
+            WaitError::Poll(Error::Fatal)
+        ]
+    ));
+    assert!(!err.is_timeout());
+}
+
+#[test]
+fn all_timed_out() {
+    let clock = VirtualClock::manual();
+    let waiters = [scripted(&clock, vec![]), scripted(&clock, vec![])];
+
+    let select = select_ok(waiters).with_deadline(clock.now() + secs(2));
+    let err = block_on(ticking(&clock, select.wait())).unwrap_err();
+    assert_eq!(err.errors().len(), 2);
+    assert!(err.is_timeout());
+}
+
+#[test]
+fn no_waiters_fail_without_errors() {
+    let select = select_ok(Vec::<ScriptedWaiter<u32, Error>>::new());
+    let err = block_on(select.wait()).unwrap_err();
+    assert!(err.errors().is_empty());
+    assert!(!err.is_timeout());
+}
@@ -0,0 +1,112 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiters driven by predicates with `StateWaiter`.
+
+mod common;
+
+use std::collections::VecDeque;
+use std::future::{ready, Ready};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use waiter::testing::{Clocked, VirtualClock};
+use waiter::{StateWaitError, StateWaiter, WaitError, Waiter, WaiterCurrentState, WaiterWithState};
+
+use common::{block_on, secs, Error};
+
+type Fetched = Result<u32, Error>;
+
+/// Fetch function returning `states` one by one, then repeating the last one.
+fn fetch(states: Vec<Fetched>) -> impl FnMut() -> Ready<Fetched> + Clone + Send {
+    let states = Arc::new(Mutex::new(VecDeque::from(states)));
+    move || {
+        let mut states = states.lock().unwrap();
+        let state = if states.len() > 1 {
+            states.pop_front()
+        } else {
+            states.front().copied()
+        };
+        ready(state.expect("no states to fetch"))
+    }
+}
+
+fn is_done(state: &u32) -> bool {
+    *state == 100
+}
+
+fn is_failed(state: &u32) -> bool {
+    *state > 100
+}
+
+#[test]
+fn success_predicate_finishes_waiting() {
+    let waiter = StateWaiter::new(fetch(vec![Ok(1), Ok(50), Ok(100)]), is_done, is_failed);
+    let clock = VirtualClock::new();
+    let result = block_on(Clocked::new(waiter, clock.clone()).wait());
+    assert_eq!(result, Ok(100));
+    assert_eq!(clock.elapsed(), secs(2));
+}
+
+#[test]
+fn failure_predicate_fails_waiting() {
+    let waiter = StateWaiter::new(fetch(vec![Ok(1), Ok(500), Ok(100)]), is_done, is_failed);
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(result, Err(StateWaitError::Failed(500)));
+}
+
+#[test]
+fn fetch_errors_fail_waiting() {
+    let waiter = StateWaiter::new(fetch(vec![Ok(1), Err(Error::Fatal)]), is_done, is_failed);
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(result, Err(StateWaitError::Fetch(Error::Fatal)));
+}
+
+#[test]
+fn timeout_reports_the_last_state() {
+    let waiter = StateWaiter::new(fetch(vec![Ok(1), Ok(2)]), is_done, is_failed)
+        .with_timeout(secs(5))
+        .with_delay(secs(2));
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait());
+    assert_eq!(
+        result,
+        Err(StateWaitError::TimedOut {
+            last_state: Some(2)
+        })
+    );
+
+    let waiter = StateWaiter::new(fetch(vec![Ok(1)]), is_done, is_failed);
+    let result = block_on(Clocked::new(waiter, VirtualClock::new()).wait_for(Duration::ZERO));
+    assert_eq!(result, Err(StateWaitError::TimedOut { last_state: None }));
+}
+
+#[test]
+fn current_state_follows_fetches() {
+    let mut waiter = StateWaiter::new(fetch(vec![Ok(1), Ok(2)]), is_done, is_failed);
+    assert_eq!(waiter.waiter_current_state(), &None);
+
+    assert_eq!(block_on(waiter.poll()), Ok(None));
+    assert_eq!(waiter.waiter_current_state(), &Some(1));
+    assert_eq!(waiter.state(), Some(&1));
+
+    assert_eq!(block_on(waiter.poll()), Ok(None));
+    assert_eq!(waiter.waiter_current_state(), &Some(2));
+}
+
+#[test]
+fn wait_with_state_reports_the_last_state() {
+    let waiter = StateWaiter::new(fetch(vec![Ok(1), Ok(7)]), is_done, is_failed);
+    let waiter = Clocked::new(waiter, VirtualClock::new());
+    let result = block_on(waiter.wait_for_with_state(secs(3)));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout {
+            attempts: 3,
+            last_state: Some(Some(7)),
+            ..
+        })
+    ));
+}
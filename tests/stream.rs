@@ -0,0 +1,172 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Waiting as a stream of events.
+
+#![cfg(feature = "stream")]
+
+mod common;
+
+use std::future::poll_fn;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_core::Stream;
+use waiter::observer::{Observer, Progress};
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{DynTimer, WaitEvent, Waiter, WaiterCurrentState};
+
+use common::{block_on, Error};
+
+/// Scripted waiter with the number of polls as its state.
+struct Counted {
+    script: ScriptedWaiter<u32, Error>,
+    polls: u32,
+}
+
+#[async_trait]
+impl Waiter<u32, Error> for Counted {
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.script.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.script.default_delay()
+    }
+
+    fn is_transient(&self, err: &Error) -> bool {
+        self.script.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        Some(1)
+    }
+
+    fn timer(&self) -> &dyn DynTimer {
+        self.script.timer()
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, Error> {
+        self.polls += 1;
+        self.script.poll().await
+    }
+
+    fn timeout_error(&self) -> Error {
+        self.script.timeout_error()
+    }
+}
+
+impl WaiterCurrentState<u32> for Counted {
+    fn waiter_current_state(&self) -> &u32 {
+        &self.polls
+    }
+}
+
+fn counted(steps: Vec<Step<u32, Error>>, clock: &VirtualClock) -> Counted {
+    let script = ScriptedWaiter::new(steps)
+        .with_transient(|err| *err == Error::Transient)
+        .with_timeout(Duration::from_secs(10))
+        .with_delay(Duration::from_secs(3))
+        .with_clock(clock.clone());
+    Counted { script, polls: 0 }
+}
+
+fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+    let future = async move {
+        let mut items = Vec::new();
+        while let Some(item) = poll_fn(|cx| Pin::new(&mut stream).poll_next(cx)).await {
+            items.push(item);
+        }
+        items
+    };
+    block_on(future)
+}
+
+#[test]
+fn stream_reports_transient_errors_and_result() {
+    let clock = VirtualClock::new();
+    let waiter = counted(
+        vec![Step::Pending, Step::Err(Error::Transient), Step::Done(42)],
+        &clock,
+    );
+    let items = collect(waiter.into_stream());
+    assert_eq!(
+        items,
+        [
+            WaitEvent::Pending(1),
+            WaitEvent::Err(Error::Transient),
+            WaitEvent::Done(42),
+        ]
+    );
+    clock.assert_sleeps(&[Duration::from_secs(3), Duration::from_secs(3)]);
+}
+
+#[test]
+fn stream_ends_on_fatal_error() {
+    let clock = VirtualClock::new();
+    let waiter = counted(
+        vec![
+            Step::Err(Error::Transient),
+            Step::Err(Error::Transient),
+            Step::Done(42),
+        ],
+        &clock,
+    );
+    // Only one consecutive transient failure is allowed.
+    let items = collect(waiter.into_stream());
+    assert_eq!(
+        items,
+        [
+            WaitEvent::Err(Error::Transient),
+            WaitEvent::Err(Error::Transient),
+        ]
+    );
+
+    let waiter = counted(vec![Step::Err(Error::Fatal)], &clock);
+    let items = collect(waiter.into_stream());
+    assert_eq!(items, [WaitEvent::Err(Error::Fatal)]);
+}
+
+#[test]
+fn stream_times_out_at_the_deadline() {
+    let clock = VirtualClock::new();
+    let waiter = counted(vec![], &clock);
+    let items = collect(waiter.into_stream());
+    assert_eq!(
+        items,
+        [
+            WaitEvent::Pending(1),
+            WaitEvent::Pending(2),
+            WaitEvent::Pending(3),
+            WaitEvent::Pending(4),
+            WaitEvent::TimedOut,
+        ]
+    );
+    assert_eq!(clock.elapsed(), Duration::from_secs(10));
+}
+
+#[derive(Clone, Default)]
+struct Recorder {
+    successes: Arc<Mutex<Vec<u32>>>,
+}
+
+impl Observer<u32, Error> for Recorder {
+    fn on_success(&mut self, result: &u32, _progress: &Progress) {
+        self.successes.lock().unwrap().push(*result);
+    }
+}
+
+#[test]
+fn stream_notifies_observers() {
+    let clock = VirtualClock::new();
+    let recorder = Recorder::default();
+    let waiter = counted(vec![Step::Done(42)], &clock).with_observer(recorder.clone());
+    let items = collect(waiter.into_stream());
+    assert_eq!(items, [WaitEvent::Done(42)]);
+    assert_eq!(*recorder.successes.lock().unwrap(), [42]);
+}
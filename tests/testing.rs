@@ -0,0 +1,120 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! The testing helpers themselves.
+
+mod common;
+
+use std::time::Duration;
+
+use waiter::testing::{Clocked, ScriptedWaiter, Step, VirtualClock};
+use waiter::{CancellationToken, TimedOut, Timer, WaitError, Waiter};
+
+use common::{block_on, runtime, secs, ticking};
+
+#[test]
+fn auto_clock_advances_on_sleep() {
+    let clock = VirtualClock::new();
+    let start = clock.now();
+
+    block_on(clock.sleep(secs(5)));
+    block_on(clock.sleep(secs(2)));
+    assert_eq!(clock.now() - start, secs(7));
+    assert_eq!(clock.elapsed(), secs(7));
+    clock.assert_sleeps(&[secs(5), secs(2)]);
+}
+
+#[test]
+fn manual_clock_waits_for_advance() {
+    let clock = VirtualClock::manual();
+    let waiter = ScriptedWaiter::<_, TimedOut>::new([Step::Pending, Step::Done(42)])
+        .with_clock(clock.clone());
+
+    runtime().block_on(async {
+        let wait = tokio::spawn(waiter.clone().wait());
+        tokio::task::yield_now().await;
+        // The first poll has been made, the loop is sleeping.
+        waiter.assert_polls(1);
+        clock.assert_sleeps(&[secs(1)]);
+
+        clock.advance(Duration::from_millis(500));
+        tokio::task::yield_now().await;
+        waiter.assert_polls(1);
+
+        clock.advance(Duration::from_millis(500));
+        assert_eq!(wait.await.unwrap(), Ok(42));
+    });
+    waiter.assert_polls(2);
+    assert_eq!(clock.elapsed(), secs(1));
+}
+
+#[test]
+fn scripted_waiter_follows_the_script() {
+    let waiter = ScriptedWaiter::<u32, TimedOut>::new([Step::Pending, Step::Err(TimedOut)]);
+    assert_eq!(waiter.remaining(), 2);
+
+    let mut polled = waiter.clone();
+    assert_eq!(block_on(polled.poll()), Ok(None));
+    assert_eq!(block_on(polled.poll()), Err(TimedOut));
+    // An exhausted script keeps returning `Ok(None)`.
+    assert_eq!(block_on(polled.poll()), Ok(None));
+
+    waiter.assert_polls(3);
+    assert_eq!(waiter.remaining(), 0);
+}
+
+#[test]
+fn clocked_waiter_uses_the_clock() {
+    let clock = VirtualClock::new();
+    let scripted =
+        ScriptedWaiter::<_, TimedOut>::new([Step::Pending, Step::Pending, Step::Done(1)])
+            .with_delay(secs(3));
+    let waiter = Clocked::new(scripted.clone(), clock.clone());
+
+    assert_eq!(block_on(waiter.wait_for(secs(60))), Ok(1));
+    scripted.assert_polls(3);
+    clock.assert_sleeps(&[secs(3), secs(3)]);
+}
+
+#[test]
+fn scripted_waiter_records_cancellation() {
+    let waiter = ScriptedWaiter::<u32, TimedOut>::new([]).with_clock(VirtualClock::new());
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let result = block_on(waiter.clone().wait_cancellable(token));
+    assert_eq!(result, Err(WaitError::Cancelled));
+    assert!(waiter.is_cancelled());
+    waiter.assert_polls(0);
+}
+
+#[test]
+fn ticking_advances_manual_clock() {
+    let clock = VirtualClock::manual();
+    let waiter = ScriptedWaiter::<_, TimedOut>::new([Step::Pending, Step::Pending, Step::Done(1)])
+        .with_delay(secs(2))
+        .with_clock(clock.clone());
+
+    let result = block_on(ticking(&clock, waiter.clone().wait()));
+    assert_eq!(result, Ok(1));
+    assert!(!waiter.is_cancelled());
+    assert_eq!(clock.elapsed(), secs(4));
+}
+
+#[test]
+#[should_panic(expected = "unexpected sleeps of the virtual clock")]
+fn assert_sleeps_detects_mismatch() {
+    let clock = VirtualClock::new();
+    block_on(clock.sleep(secs(1)));
+    clock.assert_sleeps(&[secs(2)]);
+}
+
+#[test]
+#[should_panic(expected = "unexpected number of polls")]
+fn assert_polls_detects_mismatch() {
+    let waiter = ScriptedWaiter::<u32, TimedOut>::new([]);
+    waiter.assert_polls(1);
+}
@@ -0,0 +1,312 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Invariants of the waiting loop, checked with the virtual clock.
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use waiter::testing::{ScriptedWaiter, Step, VirtualClock};
+use waiter::{TimedOut, Timer, WaitError, WaitOptions, Waiter};
+
+use common::{block_on, secs, Error};
+
+fn pending(clock: &VirtualClock) -> ScriptedWaiter<(), TimedOut> {
+    ScriptedWaiter::new([]).with_clock(clock.clone())
+}
+
+#[test]
+fn deadline_in_the_past_makes_no_polls() {
+    let clock = VirtualClock::new();
+    let waiter = pending(&clock);
+    let deadline = clock.now() - secs(5);
+
+    let result = block_on(waiter.clone().wait_until(deadline));
+    assert_eq!(result, Err(TimedOut));
+    waiter.assert_polls(0);
+
+    let result = block_on(
+        waiter
+            .clone()
+            .wait_with(WaitOptions::new().with_deadline(deadline)),
+    );
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 0, .. })
+    ));
+    waiter.assert_polls(0);
+    clock.assert_sleeps(&[]);
+}
+
+#[test]
+fn final_sleep_is_shortened_to_the_deadline() {
+    let clock = VirtualClock::new();
+    let waiter = pending(&clock).with_delay(secs(3));
+
+    let result = block_on(waiter.clone().wait_for_detailed(secs(10)));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 4, elapsed, .. }) if elapsed == secs(10)
+    ));
+    // Polls at 0, 3, 6 and 9 seconds, none at the deadline.
+    waiter.assert_polls(4);
+    clock.assert_sleeps(&[secs(3), secs(3), secs(3), secs(1)]);
+    assert_eq!(clock.elapsed(), secs(10));
+}
+
+#[test]
+fn first_poll_happens_before_the_deadline() {
+    let clock = VirtualClock::new();
+    let waiter = ScriptedWaiter::<_, TimedOut>::new([Step::Done(42)]).with_clock(clock.clone());
+
+    let result = block_on(waiter.clone().wait_until(clock.now() + secs(1)));
+    assert_eq!(result, Ok(42));
+    waiter.assert_polls(1);
+    clock.assert_sleeps(&[]);
+}
+
+fn scripted(clock: &VirtualClock, steps: Vec<Step<u32, Error>>) -> ScriptedWaiter<u32, Error> {
+    ScriptedWaiter::new(steps)
+        .with_transient(|err| matches!(err, Error::Transient | Error::PollTimedOut))
+        .with_clock(clock.clone())
+}
+
+/// Scripted waiter with limits that `ScriptedWaiter` does not provide.
+struct Limited {
+    inner: ScriptedWaiter<u32, Error>,
+    max_transient_failures: Option<u32>,
+    poll_timeout: Option<Duration>,
+    poll_timeout_error: Error,
+    /// Whether `poll` never finishes.
+    hang: bool,
+}
+
+impl Limited {
+    fn new(inner: ScriptedWaiter<u32, Error>) -> Limited {
+        Limited {
+            inner,
+            max_transient_failures: None,
+            poll_timeout: None,
+            poll_timeout_error: Error::PollTimedOut,
+            hang: false,
+        }
+    }
+}
+
+#[async_trait]
+impl Waiter<u32, Error> for Limited {
+    fn default_wait_timeout(&self) -> Option<Duration> {
+        self.inner.default_wait_timeout()
+    }
+
+    fn default_delay(&self) -> Duration {
+        self.inner.default_delay()
+    }
+
+    fn is_transient(&self, err: &Error) -> bool {
+        self.inner.is_transient(err)
+    }
+
+    fn default_max_transient_failures(&self) -> Option<u32> {
+        self.max_transient_failures
+    }
+
+    fn default_poll_timeout(&self) -> Option<Duration> {
+        self.poll_timeout
+    }
+
+    fn poll_timeout_error(&self) -> Error {
+        self.poll_timeout_error
+    }
+
+    fn timer(&self) -> &dyn waiter::DynTimer {
+        self.inner.timer()
+    }
+
+    async fn poll(&mut self) -> Result<Option<u32>, Error> {
+        if self.hang {
+            // Still counted as a poll by the script.
+            let _ = self.inner.poll().await;
+            std::future::pending::<()>().await;
+        }
+        self.inner.poll().await
+    }
+
+    fn timeout_error(&self) -> Error {
+        Error::TimedOut
+    }
+}
+
+#[test]
+fn max_attempts_stops_waiting() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(&clock, vec![]);
+
+    let result = block_on(waiter.clone().wait_attempts(3));
+    assert!(matches!(
+        result,
+        Err(WaitError::AttemptsExhausted { attempts: 3, elapsed, .. }) if elapsed == secs(2)
+    ));
+    waiter.assert_polls(3);
+    // No sleep after the last attempt.
+    clock.assert_sleeps(&[secs(1), secs(1)]);
+}
+
+#[test]
+fn whichever_limit_is_reached_first_stops_waiting() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(&clock, vec![]);
+
+    let result = block_on(waiter.wait_for_attempts(secs(60), 2));
+    assert!(matches!(
+        result,
+        Err(WaitError::AttemptsExhausted { attempts: 2, .. })
+    ));
+
+    let waiter = scripted(&clock, vec![]);
+    let result = block_on(waiter.wait_for_attempts(secs(2), 10));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 2, .. })
+    ));
+}
+
+#[test]
+fn transient_errors_are_retried() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(
+        &clock,
+        vec![Step::Err(Error::Transient), Step::Pending, Step::Done(7)],
+    );
+
+    assert_eq!(block_on(waiter.clone().wait()), Ok(7));
+    waiter.assert_polls(3);
+    assert_eq!(waiter.remaining(), 0);
+}
+
+#[test]
+fn fatal_errors_stop_waiting() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(&clock, vec![Step::Err(Error::Fatal), Step::Done(7)]);
+
+    assert_eq!(block_on(waiter.clone().wait()), Err(Error::Fatal));
+    waiter.assert_polls(1);
+    clock.assert_sleeps(&[]);
+}
+
+#[test]
+fn last_transient_error_is_reported_on_timeout() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(
+        &clock,
+        vec![Step::Err(Error::Transient), Step::Err(Error::Transient)],
+    );
+
+    let result = block_on(waiter.clone().wait_for_detailed(secs(5)));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout {
+            attempts: 5,
+            last_error: Some(Error::Transient),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn max_transient_failures_makes_errors_fatal() {
+    let clock = VirtualClock::new();
+    let script = scripted(
+        &clock,
+        vec![
+            Step::Err(Error::Transient),
+            Step::Err(Error::Transient),
+            Step::Pending,
+            Step::Err(Error::Transient),
+            Step::Err(Error::Transient),
+            Step::Err(Error::Transient),
+            Step::Done(7),
+        ],
+    );
+    let waiter = Limited {
+        max_transient_failures: Some(2),
+        ..Limited::new(script.clone())
+    };
+
+    // The counter is reset by the successful poll in between.
+    let result = block_on(waiter.wait_for_detailed(secs(60)));
+    assert_eq!(result, Err(WaitError::Poll(Error::Transient)));
+    script.assert_polls(6);
+}
+
+#[test]
+fn initial_delay_counts_against_the_timeout() {
+    let clock = VirtualClock::new();
+    let waiter = scripted(&clock, vec![Step::Done(7)]);
+    let options = WaitOptions::new()
+        .with_timeout(secs(5))
+        .with_initial_delay(secs(10));
+
+    let result = block_on(waiter.clone().wait_with(options));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout { attempts: 0, elapsed, .. }) if elapsed == secs(5)
+    ));
+    waiter.assert_polls(0);
+    clock.assert_sleeps(&[secs(5)]);
+
+    let clock = VirtualClock::new();
+    let waiter = scripted(&clock, vec![Step::Done(7)]);
+    let options = WaitOptions::new()
+        .with_timeout(secs(5))
+        .with_initial_delay(secs(2));
+    assert_eq!(block_on(waiter.clone().wait_with(options)), Ok(7));
+    clock.assert_sleeps(&[secs(2)]);
+}
+
+#[test]
+fn hanging_poll_times_out_as_transient_error() {
+    let clock = VirtualClock::new();
+    let script = scripted(&clock, vec![Step::Pending, Step::Pending]);
+    let waiter = Limited {
+        poll_timeout: Some(secs(2)),
+        hang: true,
+        ..Limited::new(script.clone())
+    };
+
+    let result = block_on(waiter.wait_for_detailed(secs(7)));
+    assert!(matches!(
+        result,
+        Err(WaitError::Timeout {
+            attempts: 3,
+            last_error: Some(Error::PollTimedOut),
+            ..
+        })
+    ));
+    // Poll timeouts of 2 seconds with 1 second delays, the last poll is cut
+    // short by the deadline.
+    clock.assert_sleeps(&[secs(2), secs(1), secs(2), secs(1), secs(1)]);
+    assert_eq!(clock.elapsed(), secs(7));
+}
+
+#[test]
+fn hanging_poll_times_out_as_fatal_error() {
+    let clock = VirtualClock::new();
+    let script = scripted(&clock, vec![]);
+    let waiter = Limited {
+        poll_timeout: Some(secs(2)),
+        poll_timeout_error: Error::Fatal,
+        hang: true,
+        ..Limited::new(script.clone())
+    };
+
+    let result = block_on(waiter.wait_for_detailed(secs(60)));
+    assert_eq!(result, Err(WaitError::Poll(Error::Fatal)));
+    script.assert_polls(1);
+    assert_eq!(clock.elapsed(), secs(2));
+}